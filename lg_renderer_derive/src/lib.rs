@@ -0,0 +1,355 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Fields, LitBool, LitInt, LitStr, Type};
+
+/// `#[uniform(binding = 0, set = 0, name = "Camera", storage, layout = "std430", update = false)]` on the struct,
+/// `#[uniform(pad)]` on padding fields. `name` defaults to the struct name, `set` to 0, the layout to std140
+/// for uniform blocks and std430 for storage buffers, `update` to true.
+/// Field offsets and the total size are checked against the layout, a derive can't add the padding itself.
+#[proc_macro_derive(LgUniform, attributes(uniform))]
+pub fn derive_lg_uniform(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_lg_uniform(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// For structs nested in uniforms, `#[uniform(pad)]` marks padding fields.
+#[proc_macro_derive(GlslType, attributes(uniform))]
+pub fn derive_glsl_type(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_glsl_type(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// `#[location = N]` overrides a field's location, the following fields continue from there.
+/// `#[normalized]` maps integer fields to [0, 1] or [-1, 1] floats instead of integer attributes.
+/// Matrix fields (`[[f32; R]; C]`) take `C` consecutive locations, fields sharing a location don't compile.
+#[proc_macro_derive(LgVertex, attributes(location, normalized))]
+pub fn derive_lg_vertex(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand_lg_vertex(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+struct UniformAttributes {
+    name: String,
+    binding: usize,
+    set: usize,
+    storage: bool,
+    std430: Option<bool>,
+    update: bool,
+}
+
+fn expand_lg_uniform(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = layout_fields(input)?;
+    let attributes = uniform_attributes(input)?;
+    let ident = &input.ident;
+
+    let UniformAttributes { name, binding, set, storage, std430, update } = attributes;
+    let std430 = std430.unwrap_or(storage);
+    let u_type = if storage { quote!(STORAGE_BUFFER) } else { quote!(STRUCT) };
+    let checks = layout_checks(ident, &fields, std430);
+
+    Ok(quote! {
+        impl ::lg_renderer::renderer_core::lg_uniform::LgUniform for #ident {
+            fn name(&self) -> &str {
+                #name
+            }
+            fn u_type(&self) -> ::lg_renderer::renderer_core::lg_uniform::LgUniformType {
+                ::lg_renderer::renderer_core::lg_uniform::LgUniformType::#u_type
+            }
+            fn binding(&self) -> usize {
+                #binding
+            }
+            fn set(&self) -> usize {
+                #set
+            }
+            fn data_size(&self) -> usize {
+                ::core::mem::size_of::<Self>()
+            }
+            fn get_raw_data(&self) -> *const ::core::ffi::c_void {
+                self as *const Self as *const ::core::ffi::c_void
+            }
+            fn set_data<D>(&mut self, data: &D) {
+                assert_eq!(
+                    ::core::mem::size_of::<D>(),
+                    ::core::mem::size_of::<Self>(),
+                    "set_data needs a value of the same size as the uniform!"
+                );
+                unsafe {
+                    ::core::ptr::copy_nonoverlapping(
+                        data as *const D as *const u8,
+                        self as *mut Self as *mut u8,
+                        ::core::mem::size_of::<Self>()
+                    );
+                }
+            }
+            fn update_data(&self) -> bool {
+                #update
+            }
+        }
+        #checks
+    })
+}
+
+fn expand_lg_vertex(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(input.generics.span(), "vertex structs can't be generic"));
+    }
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(input.ident.span(), "only structs can be vertices"));
+    };
+    let Fields::Named(named) = &data.fields else {
+        return Err(syn::Error::new(data.fields.span(), "vertex structs need named fields"));
+    };
+    let ident = &input.ident;
+
+    let mut pushes = Vec::new();
+    let mut checks = Vec::new();
+    let mut ranges = Vec::new();
+    for (index, field) in named.named.iter().enumerate() {
+        let name = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let mut location = quote!(location);
+        let mut normalized = false;
+
+        for attr in &field.attrs {
+            if attr.path().is_ident("location") {
+                let value: LitInt = match &attr.meta {
+                    syn::Meta::NameValue(meta) => syn::parse2(meta.value.to_token_stream())?,
+                    _ => attr.parse_args()?,
+                };
+                let value: u32 = value.base10_parse()?;
+                location = quote!(#value);
+            } else if attr.path().is_ident("normalized") {
+                attr.meta.require_path_only()?;
+                normalized = true;
+            }
+        }
+
+        pushes.push(quote! {
+            location = ::lg_renderer::renderer_core::lg_vertex::push_attribute::<#ty>(
+                &mut attributes,
+                #location,
+                ::core::mem::offset_of!(#ident, #name),
+                #normalized
+            );
+        });
+        ranges.push(quote! {
+            let start = #location;
+            location = start + <#ty as ::lg_renderer::renderer_core::lg_vertex::VertexAttributeType>::LOCATIONS;
+            ranges[#index] = (start, location);
+        });
+        if normalized {
+            let message = format!("`{}::{}` is #[normalized] but doesn't have integer components", ident, name);
+            checks.push(quote! {
+                assert!(<#ty as ::lg_renderer::renderer_core::lg_vertex::VertexAttributeType>::COMPONENT.is_integer(), #message);
+            });
+        }
+    }
+
+    // Matrices span several locations, so every pair of fields is checked for overlapping ranges.
+    let names: Vec<_> = named.named.iter().map(|field| field.ident.as_ref().unwrap()).collect();
+    for (a, first) in names.iter().enumerate() {
+        for (b, second) in names.iter().enumerate().skip(a + 1) {
+            let message = format!("`{}::{}` and `{}::{}` use the same location", ident, first, ident, second);
+            checks.push(quote! {
+                assert!(ranges[#a].1 <= ranges[#b].0 || ranges[#b].1 <= ranges[#a].0, #message);
+            });
+        }
+    }
+    let count = names.len();
+
+    Ok(quote! {
+        impl ::lg_renderer::renderer_core::lg_vertex::GlVertex for #ident {
+            #[allow(unused_mut, unused_assignments)]
+            fn gl_info() -> Vec<::lg_renderer::renderer_core::lg_vertex::VertexAttribute> {
+                let mut attributes = Vec::new();
+                let mut location = 0;
+                #(#pushes)*
+
+                attributes
+            }
+        }
+        impl ::lg_renderer::renderer_core::lg_vertex::LgVertex for #ident {}
+        #[allow(unused_mut, unused_assignments, unused_variables)]
+        const _: () = {
+            let mut ranges = [(0u32, 0u32); #count];
+            let mut location = 0u32;
+            #(#ranges)*
+            #(#checks)*
+        };
+    })
+}
+
+fn expand_glsl_type(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = layout_fields(input)?;
+    let ident = &input.ident;
+    let std140 = struct_layout(ident, &fields, false);
+    let std430 = struct_layout(ident, &fields, true);
+
+    Ok(quote! {
+        unsafe impl ::lg_renderer::renderer_core::lg_uniform::GlslType for #ident {
+            const STD140: ::lg_renderer::renderer_core::lg_uniform::GlslLayout = #std140;
+            const STD430: ::lg_renderer::renderer_core::lg_uniform::GlslLayout = #std430;
+        }
+        unsafe impl ::lg_renderer::renderer_core::lg_uniform::GlslArrayElement for #ident {}
+    })
+}
+
+/// Non padding fields, also makes sure the struct can have a checked layout at all.
+fn layout_fields(input: &DeriveInput) -> syn::Result<Vec<(syn::Ident, Type)>> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(input.generics.span(), "uniform structs can't be generic"));
+    }
+
+    let mut repr_c = false;
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("repr")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("C") {
+                repr_c = true;
+            }
+            Ok(())
+        })?;
+    }
+    if !repr_c {
+        return Err(syn::Error::new(input.ident.span(), "uniform structs need #[repr(C)] for their layout to be checked"));
+    }
+
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(input.ident.span(), "only structs can be uniforms"));
+    };
+    let Fields::Named(named) = &data.fields else {
+        return Err(syn::Error::new(data.fields.span(), "uniform structs need named fields"));
+    };
+
+    let mut fields = Vec::new();
+    for field in &named.named {
+        let mut pad = false;
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("uniform")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("pad") {
+                    pad = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `pad`"))
+                }
+            })?;
+        }
+        if !pad {
+            fields.push((field.ident.clone().unwrap(), field.ty.clone()));
+        }
+    }
+
+    Ok(fields)
+}
+
+fn uniform_attributes(input: &DeriveInput) -> syn::Result<UniformAttributes> {
+    let mut attributes = UniformAttributes {
+        name: input.ident.to_string(),
+        binding: 0,
+        set: 0,
+        storage: false,
+        std430: None,
+        update: true,
+    };
+    let mut has_binding = false;
+
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("uniform")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                attributes.name = meta.value()?.parse::<LitStr>()?.value();
+            } else if meta.path.is_ident("binding") {
+                attributes.binding = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+                has_binding = true;
+            } else if meta.path.is_ident("set") {
+                attributes.set = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+            } else if meta.path.is_ident("storage") {
+                attributes.storage = true;
+            } else if meta.path.is_ident("update") {
+                attributes.update = meta.value()?.parse::<LitBool>()?.value;
+            } else if meta.path.is_ident("layout") {
+                let layout = meta.value()?.parse::<LitStr>()?;
+                attributes.std430 = Some(match layout.value().as_str() {
+                    "std140" => false,
+                    "std430" => true,
+                    _ => return Err(syn::Error::new(layout.span(), "expected \"std140\" or \"std430\"")),
+                });
+            } else {
+                return Err(meta.error("expected `name`, `binding`, `set`, `storage`, `layout` or `update`"));
+            }
+            Ok(())
+        })?;
+    }
+    if !has_binding {
+        return Err(syn::Error::new(input.ident.span(), "missing #[uniform(binding = N)]"));
+    }
+    if attributes.std430 == Some(true) && !attributes.storage {
+        return Err(syn::Error::new(input.ident.span(), "uniform blocks can only use std140, std430 needs `storage`"));
+    }
+
+    Ok(attributes)
+}
+
+fn rule_tokens(std430: bool) -> (TokenStream2, TokenStream2, &'static str) {
+    if std430 {
+        (quote!(STD430), quote!(::lg_renderer::renderer_core::lg_uniform::GlslLayoutRule::STD430), "std430")
+    } else {
+        (quote!(STD140), quote!(::lg_renderer::renderer_core::lg_uniform::GlslLayoutRule::STD140), "std140")
+    }
+}
+
+fn struct_layout(ident: &syn::Ident, fields: &[(syn::Ident, Type)], std430: bool) -> TokenStream2 {
+    let (constant, rule, _) = rule_tokens(std430);
+    let steps = fields.iter().map(|(name, ty)| quote! {
+        let layout = layout.field(
+            <#ty as ::lg_renderer::renderer_core::lg_uniform::GlslType>::#constant,
+            ::core::mem::offset_of!(#ident, #name)
+        );
+    });
+
+    quote! {{
+        let layout = ::lg_renderer::renderer_core::lg_uniform::GlslStructLayout::new(#rule);
+        #(#steps)*
+        layout.finish(::core::mem::size_of::<#ident>())
+    }}
+}
+
+/// Compile time assertions naming the first field that doesn't line up.
+fn layout_checks(ident: &syn::Ident, fields: &[(syn::Ident, Type)], std430: bool) -> TokenStream2 {
+    let (constant, rule, rule_name) = rule_tokens(std430);
+    let steps = fields.iter().map(|(name, ty)| {
+        let type_message = format!("`{}::{}` has no {} layout matching its Rust type", ident, name, rule_name);
+        let offset_message = format!(
+            "`{}::{}` isn't at its {} offset, padding before it is missing or wrong",
+            ident,
+            name,
+            rule_name
+        );
+
+        quote! {
+            assert!(<#ty as ::lg_renderer::renderer_core::lg_uniform::GlslType>::#constant.matches, #type_message);
+            let layout = layout.field(
+                <#ty as ::lg_renderer::renderer_core::lg_uniform::GlslType>::#constant,
+                ::core::mem::offset_of!(#ident, #name)
+            );
+            assert!(layout.offset_matches, #offset_message);
+        }
+    });
+    let size_message = format!("size of `{}` doesn't match its {} size, trailing padding is missing or wrong", ident, rule_name);
+
+    quote! {
+        const _: () = {
+            let layout = ::lg_renderer::renderer_core::lg_uniform::GlslStructLayout::new(#rule);
+            #(#steps)*
+            assert!(layout.finish(::core::mem::size_of::<#ident>()).matches, #size_message);
+        };
+    }
+}
\ No newline at end of file
@@ -0,0 +1,86 @@
+use std::{ffi::{c_void, CString}, mem::transmute, sync::RwLock};
+
+use glutin::display::GlDisplay;
+
+use crate::gl_check;
+
+use super::GlError;
+
+type GetTextureSamplerHandle = unsafe extern "system" fn(gl::types::GLuint, gl::types::GLuint) -> gl::types::GLuint64;
+type MakeTextureHandleResident = unsafe extern "system" fn(gl::types::GLuint64);
+type MakeTextureHandleNonResident = unsafe extern "system" fn(gl::types::GLuint64);
+
+/// ARB_bindless_texture entry points, the gl crate only loads core functions.
+#[derive(Clone, Copy)]
+struct BindlessFns {
+    get_texture_sampler_handle: GetTextureSamplerHandle,
+    make_texture_handle_resident: MakeTextureHandleResident,
+    make_texture_handle_non_resident: MakeTextureHandleNonResident,
+}
+
+/// Set when the context is loaded, like the GL function pointers.
+static BINDLESS: RwLock<Option<BindlessFns>> = RwLock::new(None);
+
+/// Loads the extension functions, clears them when `supported` is false so a previous context's pointers aren't reused.
+pub(crate) fn load_bindless(display: &glutin::display::Display, supported: bool) {
+    let load = |name: &str| {
+        let name = CString::new(name).unwrap();
+        let ptr = display.get_proc_address(name.as_c_str());
+        (!ptr.is_null()).then_some(ptr)
+    };
+
+    // SAFETY: `supported` is only true when the context lists GL_ARB_bindless_texture, so the non-null
+    // pointers are the extension's entry points and match the signatures declared above.
+    let fns = supported.then(|| unsafe {
+        Some(BindlessFns {
+            get_texture_sampler_handle: transmute::<*const c_void, GetTextureSamplerHandle>(
+                load("glGetTextureSamplerHandleARB")?
+            ),
+            make_texture_handle_resident: transmute::<*const c_void, MakeTextureHandleResident>(
+                load("glMakeTextureHandleResidentARB")?
+            ),
+            make_texture_handle_non_resident: transmute::<*const c_void, MakeTextureHandleNonResident>(
+                load("glMakeTextureHandleNonResidentARB")?
+            ),
+        })
+    }).flatten();
+
+    *BINDLESS.write().unwrap() = fns;
+}
+pub(crate) fn bindless() -> bool {
+    BINDLESS.read().unwrap().is_some()
+}
+
+fn fns() -> Result<BindlessFns, GlError> {
+    BINDLESS.read().unwrap().ok_or_else(|| GlError::Error(
+        "Bindless textures unsupported".to_string(),
+        "GL_ARB_bindless_texture isn't available!".to_string()
+    ))
+}
+/// After this the texture and sampler parameters can't change anymore.
+pub(crate) fn texture_sampler_handle(
+    texture: gl::types::GLuint,
+    sampler: gl::types::GLuint
+) -> Result<gl::types::GLuint64, GlError>
+{
+    let fns = fns()?;
+    let handle;
+    gl_check!(handle = (fns.get_texture_sampler_handle)(texture, sampler), "Failed to get texture handle!")?;
+
+    if handle == 0 {
+        return Err(GlError::Error(
+            "Invalid texture handle".to_string(),
+            format!("Failed to get a handle for texture {} with sampler {}!", texture, sampler)
+        ));
+    }
+
+    Ok(handle)
+}
+pub(crate) fn make_resident(handle: gl::types::GLuint64) -> Result<(), GlError> {
+    let fns = fns()?;
+    gl_check!((fns.make_texture_handle_resident)(handle), "Failed to make texture handle resident!")
+}
+pub(crate) fn make_non_resident(handle: gl::types::GLuint64) -> Result<(), GlError> {
+    let fns = fns()?;
+    gl_check!((fns.make_texture_handle_non_resident)(handle), "Failed to make texture handle non resident!")
+}
\ No newline at end of file
@@ -1,8 +1,8 @@
 use std::{ffi::c_void, hash::Hash};
 
-use crate::gl_check;
+use crate::{gl_check, renderer_core::lg_buffer::BufferUsage};
 
-use super::GlError;
+use super::{gl_capabilities::{dsa, supported, GlFeature}, GlError};
 
 #[derive(Debug, PartialEq, Eq)]
 pub(crate) struct GlBuffer {
@@ -12,37 +12,17 @@ pub(crate) struct GlBuffer {
 impl GlBuffer {
     pub(crate) fn new(target: gl::types::GLuint) -> Result<Self, GlError> {
         let mut id = 0;
-        gl_check!(gl::GenBuffers(1, &mut id), "Failed to create buffer!")?;
+        if dsa() {
+            gl_check!(gl::CreateBuffers(1, &mut id), "Failed to create buffer!")?;
+        } else {
+            gl_check!(gl::GenBuffers(1, &mut id), "Failed to create buffer!")?;
+        }
         
         Ok(Self { id, target })
     }
-    pub fn bind(&self) -> Result<(), GlError> {
-        gl_check!(gl::BindBuffer(self.target, self.id), "Failed to bind buffer!")
-    }
-    pub fn bind_base(&self, binding_index: usize) -> Result<(), GlError> {
-        gl_check!(
-            gl::BindBufferBase(
-                self.target, 
-                binding_index as gl::types::GLuint, 
-                self.id
-            ), 
-            "Failed to bind base!"
-        )
-    }
-    pub fn unbind(&self) -> Result<(), GlError> {
-        gl_check!(gl::BindBuffer(self.target, 0), "Failed to unbind buffer!")
-    }
     pub fn set_data<D>(&self, data: &[D], usage: gl::types::GLuint) -> Result<(), GlError> {
         let (_, data_bytes, _) = unsafe { data.align_to::<u8>() };
-        gl_check!(
-            gl::BufferData(
-                self.target,
-                data_bytes.len() as gl::types::GLsizeiptr,
-                data_bytes.as_ptr() as *const _,
-                usage
-            ),
-            "Failed to set data!"
-        )
+        self.set_data_full(data_bytes.len(), data_bytes.as_ptr() as *const _, usage)
     }
     pub fn set_data_full(
         &self,
@@ -51,24 +31,138 @@ impl GlBuffer {
         usage: gl::types::GLuint
     ) -> Result<(), GlError> 
     {
-        gl_check!(
-            gl::BufferData(
-                self.target, 
-                size as gl::types::GLsizeiptr, 
-                data, 
-                usage
-            ),
-            "Failed to set data full!"
-        )
+        if dsa() {
+            gl_check!(
+                gl::NamedBufferData(
+                    self.id, 
+                    size as gl::types::GLsizeiptr, 
+                    data, 
+                    usage
+                ),
+                "Failed to set data full!"
+            )
+        } else {
+            gl_check!(
+                gl::BufferData(
+                    self.target, 
+                    size as gl::types::GLsizeiptr, 
+                    data, 
+                    usage
+                ),
+                "Failed to set data full!"
+            )
+        }
+    }
+    /// `offset` is in bytes.
+    pub fn sub_data<D>(&self, offset: usize, data: &[D]) -> Result<(), GlError> {
+        let (_, data_bytes, _) = unsafe { data.align_to::<u8>() };
+        if dsa() {
+            gl_check!(
+                gl::NamedBufferSubData(
+                    self.id,
+                    offset as gl::types::GLintptr,
+                    data_bytes.len() as gl::types::GLsizeiptr,
+                    data_bytes.as_ptr() as *const _
+                ),
+                "Failed to set sub data!"
+            )
+        } else {
+            gl_check!(
+                gl::BufferSubData(
+                    self.target,
+                    offset as gl::types::GLintptr,
+                    data_bytes.len() as gl::types::GLsizeiptr,
+                    data_bytes.as_ptr() as *const _
+                ),
+                "Failed to set sub data!"
+            )
+        }
+    }
+    /// Without DSA the buffer has to be bound to its target beforehand.
+    pub fn size(&self) -> Result<usize, GlError> {
+        let mut size = 0;
+        if dsa() {
+            gl_check!(gl::GetNamedBufferParameteri64v(self.id, gl::BUFFER_SIZE, &mut size), "Failed to get buffer size!")?;
+        } else {
+            gl_check!(gl::GetBufferParameteri64v(self.target, gl::BUFFER_SIZE, &mut size), "Failed to get buffer size!")?;
+        }
+
+        Ok(size as usize)
+    }
+    /// Reads `data.len()` bytes starting at byte `offset`, GLES has no `glGetBufferSubData` so it maps the range there.
+    /// Without DSA the buffer has to be bound to its target beforehand.
+    pub fn get_sub_data(&self, offset: usize, data: &mut [u8]) -> Result<(), GlError> {
+        if dsa() {
+            return gl_check!(
+                gl::GetNamedBufferSubData(
+                    self.id,
+                    offset as gl::types::GLintptr,
+                    data.len() as gl::types::GLsizeiptr,
+                    data.as_mut_ptr() as *mut _
+                ),
+                "Failed to get sub data!"
+            );
+        }
+        if supported(GlFeature::BUFFER_READBACK) {
+            return gl_check!(
+                gl::GetBufferSubData(
+                    self.target,
+                    offset as gl::types::GLintptr,
+                    data.len() as gl::types::GLsizeiptr,
+                    data.as_mut_ptr() as *mut _
+                ),
+                "Failed to get sub data!"
+            );
+        }
+
+        let mapped = self.map_range(offset, data.len(), gl::MAP_READ_BIT)? as *const u8;
+        unsafe { std::ptr::copy_nonoverlapping(mapped, data.as_mut_ptr(), data.len()) };
+        self.unmap()
+    }
+    /// Copies `size` bytes of `src` starting at byte `src_offset` to the start of this buffer.
+    /// Without DSA `src` has to be bound to `GL_COPY_READ_BUFFER` and `self` to its target.
+    pub fn copy_from(&self, src: &GlBuffer, src_offset: usize, size: usize) -> Result<(), GlError> {
+        let (src_offset, size) = (src_offset as gl::types::GLintptr, size as gl::types::GLsizeiptr);
+        if dsa() {
+            gl_check!(
+                gl::CopyNamedBufferSubData(src.id, self.id, src_offset, 0, size),
+                "Failed to copy buffer!"
+            )
+        } else {
+            gl_check!(
+                gl::CopyBufferSubData(gl::COPY_READ_BUFFER, self.target, src_offset, 0, size),
+                "Failed to copy buffer!"
+            )
+        }
     }
-    pub fn map(&self, access: gl::types::GLenum) -> Result<*mut std::ffi::c_void, GlError> {
+    pub fn map_range(
+        &self,
+        offset: usize,
+        size: usize,
+        access: gl::types::GLbitfield
+    ) -> Result<*mut std::ffi::c_void, GlError>
+    {
         let result;
-        gl_check!(result = gl::MapBuffer(self.target, access), "Failed to map buffer!")?;
+        if dsa() {
+            gl_check!(
+                result = gl::MapNamedBufferRange(self.id, offset as gl::types::GLintptr, size as gl::types::GLsizeiptr, access),
+                "Failed to map buffer range!"
+            )?;
+        } else {
+            gl_check!(
+                result = gl::MapBufferRange(self.target, offset as gl::types::GLintptr, size as gl::types::GLsizeiptr, access),
+                "Failed to map buffer range!"
+            )?;
+        }
         
         Ok(result)
     }
     pub fn unmap(&self) -> Result<(), GlError> {
-        gl_check!(gl::UnmapBuffer(self.target), "Failed to unmap buffer!")
+        if dsa() {
+            gl_check!(gl::UnmapNamedBuffer(self.id), "Failed to unmap buffer!")
+        } else {
+            gl_check!(gl::UnmapBuffer(self.target), "Failed to unmap buffer!")
+        }
     }
     pub fn id(&self) -> gl::types::GLuint {
         self.id
@@ -86,4 +180,12 @@ impl Drop for GlBuffer {
     fn drop(&mut self) {
         gl_check!(gl::DeleteBuffers(1, [self.id].as_ptr()), "Failed to delete buffer!").unwrap();
     }
+}
+
+pub(crate) fn usage_to_opengl(usage: BufferUsage) -> gl::types::GLenum {
+    match usage {
+        BufferUsage::STATIC => gl::STATIC_DRAW,
+        BufferUsage::DYNAMIC => gl::DYNAMIC_DRAW,
+        BufferUsage::STREAM => gl::STREAM_DRAW,
+    }
 }
\ No newline at end of file
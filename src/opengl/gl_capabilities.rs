@@ -0,0 +1,246 @@
+use std::{collections::HashSet, ffi::CStr, sync::atomic::{AtomicBool, AtomicU32, Ordering}};
+
+use crate::{gl_check, renderer_core::{lg_texture::CompressedFormat, GlApi}};
+
+use super::{gl_sampler::MAX_TEXTURE_MAX_ANISOTROPY, gl_texture::compressed_format_to_opengl, GlError};
+
+/// Set once the context is loaded, GL function pointers are global so this is as well.
+static DIRECT_STATE_ACCESS: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn dsa() -> bool {
+    DIRECT_STATE_ACCESS.load(Ordering::Relaxed)
+}
+
+/// Optional features checked where the capabilities aren't at hand. `is_loaded` can't tell,
+/// GLX and EGL return pointers for functions the context doesn't support.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GlFeature {
+    IMAGE_LOAD_STORE,
+    TEXTURE_STORAGE,
+    TEXTURE_READBACK,
+    BUFFER_READBACK,
+    DOUBLE_ATTRIBUTES,
+    POLYGON_MODE,
+    PROGRAM_UNIFORMS,
+    BORDER_CLAMP,
+    LOD_BIAS,
+}
+
+/// One bit per `GlFeature`, set together with `DIRECT_STATE_ACCESS`.
+static FEATURES: AtomicU32 = AtomicU32::new(0);
+
+pub(crate) fn supported(feature: GlFeature) -> bool {
+    FEATURES.load(Ordering::Relaxed) & (1 << feature as u32) != 0
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GlCapabilities {
+    pub version: (u32, u32),
+    pub direct_state_access: bool,
+    /// 0 when anisotropic filtering isn't supported.
+    pub max_anisotropy: f32,
+    /// GL_ARB_bindless_texture.
+    pub bindless_texture: bool,
+    /// Immutable buffer storage, needed for persistently mapped buffers.
+    pub buffer_storage: bool,
+    pub uniform_buffer_offset_alignment: usize,
+    /// 0 when shader storage buffers aren't supported.
+    pub storage_buffer_offset_alignment: usize,
+    pub compute_shader: bool,
+    pub image_load_store: bool,
+    /// Immutable texture storage, GL 4.2 and GLES 3.0.
+    pub texture_storage: bool,
+    /// `glGetTexImage`, GLES can't read textures back directly.
+    pub texture_readback: bool,
+    /// `glGetBufferSubData`, GLES maps the buffer instead.
+    pub buffer_readback: bool,
+    /// 64-bit vertex attributes, GL 4.1.
+    pub double_attributes: bool,
+    /// Line and point polygon modes, GLES can only fill.
+    pub polygon_mode: bool,
+    /// `glProgramUniform*`, GL 4.1 and GLES 3.1.
+    pub program_uniforms: bool,
+    /// `CLAMP_TO_BORDER` and border colors, GLES 3.2 or GL_EXT_texture_border_clamp.
+    pub border_clamp: bool,
+    /// Sampler LOD bias, GLES only has it in shaders.
+    pub lod_bias: bool,
+    extensions: HashSet<String>,
+    compressed_formats: HashSet<CompressedFormat>,
+}
+impl GlCapabilities {
+    pub(crate) fn query(api: GlApi) -> Result<Self, GlError> {
+        let mut major = 0;
+        let mut minor = 0;
+        gl_check!(gl::GetIntegerv(gl::MAJOR_VERSION, &mut major), "Failed to get major version!")?;
+        gl_check!(gl::GetIntegerv(gl::MINOR_VERSION, &mut minor), "Failed to get minor version!")?;
+
+        let mut count = 0;
+        gl_check!(gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count), "Failed to get extension count!")?;
+
+        let mut extensions = HashSet::new();
+        for i in 0..count {
+            let name;
+            gl_check!(name = gl::GetStringi(gl::EXTENSIONS, i as gl::types::GLuint), "Failed to get extension name!")?;
+            if !name.is_null() {
+                let name = unsafe { CStr::from_ptr(name as *const _) };
+                extensions.insert(name.to_string_lossy().into_owned());
+            }
+        }
+
+        let version = (major as u32, minor as u32);
+        let direct_state_access = version >= (4, 5) || extensions.contains("GL_ARB_direct_state_access");
+        DIRECT_STATE_ACCESS.store(direct_state_access, Ordering::Relaxed);
+
+        let mut max_anisotropy = 0.0;
+        if version >= (4, 6)
+            || extensions.contains("GL_ARB_texture_filter_anisotropic")
+            || extensions.contains("GL_EXT_texture_filter_anisotropic")
+        {
+            gl_check!(gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy), "Failed to get max anisotropy!")?;
+        }
+
+        let compressed_formats = query_compressed_formats(api, version, &extensions)?;
+
+        let buffer_storage = gl::BufferStorage::is_loaded() && match api {
+            GlApi::OPEN_GL => version >= (4, 4) || extensions.contains("GL_ARB_buffer_storage"),
+            GlApi::GLES => false,
+        };
+        let mut uniform_alignment = 0;
+        gl_check!(
+            gl::GetIntegerv(gl::UNIFORM_BUFFER_OFFSET_ALIGNMENT, &mut uniform_alignment),
+            "Failed to get uniform buffer offset alignment!"
+        )?;
+        let mut storage_alignment = 0;
+        let storage_buffers = match api {
+            GlApi::OPEN_GL => version >= (4, 3) || extensions.contains("GL_ARB_shader_storage_buffer_object"),
+            GlApi::GLES => version >= (3, 1),
+        };
+        if storage_buffers {
+            gl_check!(
+                gl::GetIntegerv(gl::SHADER_STORAGE_BUFFER_OFFSET_ALIGNMENT, &mut storage_alignment),
+                "Failed to get shader storage buffer offset alignment!"
+            )?;
+        }
+
+        let compute_shader = gl::DispatchCompute::is_loaded() && match api {
+            GlApi::OPEN_GL => version >= (4, 3) || extensions.contains("GL_ARB_compute_shader"),
+            GlApi::GLES => version >= (3, 1),
+        };
+        let image_load_store = gl::BindImageTexture::is_loaded() && match api {
+            GlApi::OPEN_GL => version >= (4, 2) || extensions.contains("GL_ARB_shader_image_load_store"),
+            GlApi::GLES => version >= (3, 1),
+        };
+        let desktop = api == GlApi::OPEN_GL;
+        let texture_storage = !desktop || version >= (4, 2) || extensions.contains("GL_ARB_texture_storage");
+        let double_attributes = desktop && (version >= (4, 1) || extensions.contains("GL_ARB_vertex_attrib_64bit"));
+        let program_uniforms = match api {
+            GlApi::OPEN_GL => version >= (4, 1) || extensions.contains("GL_ARB_separate_shader_objects"),
+            GlApi::GLES => version >= (3, 1),
+        };
+        let border_clamp = desktop
+            || version >= (3, 2)
+            || extensions.contains("GL_EXT_texture_border_clamp")
+            || extensions.contains("GL_OES_texture_border_clamp");
+
+        let features = [
+            (GlFeature::IMAGE_LOAD_STORE, image_load_store),
+            (GlFeature::TEXTURE_STORAGE, texture_storage),
+            (GlFeature::TEXTURE_READBACK, desktop),
+            (GlFeature::BUFFER_READBACK, desktop),
+            (GlFeature::DOUBLE_ATTRIBUTES, double_attributes),
+            (GlFeature::POLYGON_MODE, desktop),
+            (GlFeature::PROGRAM_UNIFORMS, program_uniforms),
+            (GlFeature::BORDER_CLAMP, border_clamp),
+            (GlFeature::LOD_BIAS, desktop),
+        ];
+        let bits = features.iter().filter(|(_, supported)| *supported).fold(0, |bits, (feature, _)| bits | 1 << *feature as u32);
+        FEATURES.store(bits, Ordering::Relaxed);
+
+        Ok(Self {
+            version,
+            direct_state_access,
+            max_anisotropy,
+            bindless_texture: extensions.contains("GL_ARB_bindless_texture"),
+            buffer_storage,
+            uniform_buffer_offset_alignment: uniform_alignment.max(1) as usize,
+            storage_buffer_offset_alignment: storage_alignment as usize,
+            compute_shader,
+            image_load_store,
+            texture_storage,
+            texture_readback: desktop,
+            buffer_readback: desktop,
+            double_attributes,
+            polygon_mode: desktop,
+            program_uniforms,
+            border_clamp,
+            lod_bias: desktop,
+            extensions,
+            compressed_formats,
+        })
+    }
+    pub fn supports_compressed(&self, format: CompressedFormat) -> bool {
+        self.compressed_formats.contains(&format)
+    }
+    /// Every compressed format the context can sample from, in `CompressedFormat::ALL` order.
+    pub fn compressed_formats(&self) -> Vec<CompressedFormat> {
+        CompressedFormat::ALL
+            .into_iter()
+            .filter(|format| self.compressed_formats.contains(format))
+            .collect()
+    }
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.contains(name)
+    }
+}
+
+/// Formats are reported through extensions and core versions, the driver's
+/// `GL_COMPRESSED_TEXTURE_FORMATS` list is added on top as it may omit some of them.
+fn query_compressed_formats(
+    api: GlApi,
+    version: (u32, u32),
+    extensions: &HashSet<String>
+) -> Result<HashSet<CompressedFormat>, GlError>
+{
+    use CompressedFormat as C;
+
+    let has = |name: &str| extensions.contains(name);
+    let desktop = api == GlApi::OPEN_GL;
+
+    let s3tc = has("GL_EXT_texture_compression_s3tc");
+    let rgtc = (desktop && version >= (3, 0))
+        || has("GL_ARB_texture_compression_rgtc")
+        || has("GL_EXT_texture_compression_rgtc");
+    let bptc = (desktop && version >= (4, 2))
+        || has("GL_ARB_texture_compression_bptc")
+        || has("GL_EXT_texture_compression_bptc");
+    let etc2 = !desktop || version >= (4, 3) || has("GL_ARB_ES3_compatibility");
+    let astc = (!desktop && version >= (3, 2)) || has("GL_KHR_texture_compression_astc_ldr");
+
+    let mut formats: HashSet<CompressedFormat> = C::ALL
+        .into_iter()
+        .filter(|format| match format {
+            C::BC1 | C::BC1_ALPHA | C::BC2 | C::BC3 => s3tc,
+            C::BC4 | C::BC4_SIGNED | C::BC5 | C::BC5_SIGNED => rgtc,
+            C::BC6H | C::BC6H_SIGNED | C::BC7 => bptc,
+            C::ETC2_RGB | C::ETC2_RGB_A1 | C::ETC2_RGBA
+            | C::EAC_R | C::EAC_R_SIGNED | C::EAC_RG | C::EAC_RG_SIGNED => etc2,
+            _ => astc,
+        })
+        .collect();
+
+    let mut count = 0;
+    gl_check!(gl::GetIntegerv(gl::NUM_COMPRESSED_TEXTURE_FORMATS, &mut count), "Failed to get compressed format count!")?;
+    let mut listed = vec![0; count.max(0) as usize];
+    if count > 0 {
+        gl_check!(gl::GetIntegerv(gl::COMPRESSED_TEXTURE_FORMATS, listed.as_mut_ptr()), "Failed to get compressed formats!")?;
+    }
+    for format in C::ALL {
+        let Ok(gl_format) = compressed_format_to_opengl(format, false) else { continue };
+        if listed.contains(&(gl_format as i32)) {
+            formats.insert(format);
+        }
+    }
+
+    Ok(formats)
+}
\ No newline at end of file
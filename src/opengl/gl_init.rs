@@ -8,12 +8,18 @@ use glutin::{
 };
 use glutin_winit::GlWindow;
 use raw_window_handle::HasRawWindowHandle;
-use crate::{renderer_core::CreationWindowInfo, StdError};
+use crate::{renderer_core::{CreationWindowInfo, GlApi, GlContextInfo, GlProfile, SurfaceAttributes, SurfaceConfigRequest}, StdError};
 use super::GlSpecs;
 
-pub(crate) fn init_opengl(window_info: CreationWindowInfo) -> Result<(winit::window::Window, GlSpecs), StdError>
+/// Sampler objects need GL 3.3 or GLES 3.0, features of newer versions are checked when used.
+const GL_VERSIONS: [(u8, u8); 8] = [(4, 6), (4, 5), (4, 4), (4, 3), (4, 2), (4, 1), (4, 0), (3, 3)];
+const GLES_VERSIONS: [(u8, u8); 3] = [(3, 2), (3, 1), (3, 0)];
+
+pub(crate) fn init_opengl(window_info: CreationWindowInfo, context_info: &GlContextInfo) -> Result<(winit::window::Window, GlSpecs), StdError>
 {
-    let template = glutin::config::ConfigTemplateBuilder::new();
+    let surface_request = window_info.surface;
+    let template = glutin::config::ConfigTemplateBuilder::new()
+        .with_transparency(surface_request.required.transparency);
 
     let window_builder = winit::window::WindowBuilder::new()
         .with_inner_size(winit::dpi::PhysicalSize{ 
@@ -28,8 +34,17 @@ pub(crate) fn init_opengl(window_info: CreationWindowInfo) -> Result<(winit::win
     let (window, gl_config) = display_builder.build(
         window_info.event_loop.unwrap(), 
         template, 
-        gl_config_picker
+        |configs| gl_config_picker(&surface_request, configs)
     )?;
+
+    let best = config_attributes(&gl_config);
+    if !meets_requirements(&best, &surface_request.required) {
+        return Err(format!(
+            "No framebuffer config meets the requirements! Required: {:?}, best available: {:?} (OpenGL)",
+            surface_request.required,
+            best
+        ).into());
+    }
     
     let window = match window {
         Some(window) => window,
@@ -40,34 +55,154 @@ pub(crate) fn init_opengl(window_info: CreationWindowInfo) -> Result<(winit::win
     
     let gl_display = gl_config.display();
 
-    let contex_attributes = glutin::context::ContextAttributesBuilder::new()
-        .with_context_api(glutin::context::ContextApi::OpenGl(Some(glutin::context::Version::new(4, 2))))
-        .with_debug(true)
-        .build(Some(raw_window_handle));
+    let versions: &[(u8, u8)] = match context_info.api {
+        GlApi::OPEN_GL => &GL_VERSIONS,
+        GlApi::GLES => &GLES_VERSIONS,
+    };
+    let versions = versions
+        .iter()
+        .filter(|v| **v <= context_info.max_version && **v >= context_info.min_version);
+
+    let robustness = if context_info.robustness {
+        glutin::context::Robustness::RobustLoseContextOnReset
+    } else {
+        glutin::context::Robustness::NotRobust
+    };
+    
+    let mut not_current_context = None;
+    for version in versions {
+        let gl_version = glutin::context::Version::new(version.0, version.1);
+        let mut builder = glutin::context::ContextAttributesBuilder::new()
+            .with_robustness(robustness)
+            .with_debug(context_info.debug);
+
+        builder = match context_info.api {
+            GlApi::OPEN_GL => builder
+                .with_context_api(glutin::context::ContextApi::OpenGl(Some(gl_version)))
+                .with_profile(match context_info.profile {
+                    GlProfile::CORE => glutin::context::GlProfile::Core,
+                    GlProfile::COMPATIBILITY => glutin::context::GlProfile::Compatibility,
+                }),
+            GlApi::GLES => builder.with_context_api(glutin::context::ContextApi::Gles(Some(gl_version))),
+        };
+        let contex_attributes = builder.build(Some(raw_window_handle));
+        
+        if let Ok(context) = unsafe { gl_display.create_context(&gl_config, &contex_attributes) } {
+            not_current_context = Some((context, *version));
+            break;
+        }
+    }
+    let (not_current_context, version) = not_current_context.ok_or_else(|| format!(
+        "Failed to create a {:?} context between versions {:?} and {:?}! (OpenGL)",
+        context_info.api,
+        context_info.min_version,
+        context_info.max_version
+    ))?;
 
     let (gl_context, gl_surface) = unsafe { 
         let attrs = window.build_surface_attributes(Default::default());
 
         let gl_surface = gl_config.display().create_window_surface(&gl_config, &attrs)?;
 
-        (gl_display.create_context(&gl_config, &contex_attributes)?.make_current(&gl_surface)?, gl_surface)
+        (not_current_context.make_current(&gl_surface)?, gl_surface)
     };
     
     Ok((window, GlSpecs{
         gl_context,
         gl_surface,
         gl_display,
+        api: context_info.api,
+        version,
+        debug: context_info.debug,
+        srgb: (surface_request.required.srgb || surface_request.preferred.srgb) && gl_config.srgb_capable(),
+        stream_buffer_size: context_info.stream_buffer_size,
     }))
 }
 
-pub(crate) fn gl_config_picker(configs: Box<dyn Iterator<Item = glutin::config::Config> + '_>) -> glutin::config::Config {
+pub(crate) fn gl_config_picker(
+    request: &SurfaceConfigRequest,
+    configs: Box<dyn Iterator<Item = glutin::config::Config> + '_>
+) -> glutin::config::Config 
+{
     configs
-        .reduce(|accum, config| {
-            if config.num_samples() > accum.num_samples() {
-                config
-            } else {
-                accum
-            }
+        .max_by_key(|config| {
+            let have = config_attributes(config);
+            (meets_requirements(&have, &request.required), config_score(&have, &request.preferred))
         })
         .unwrap()
+}
+
+fn config_attributes(config: &glutin::config::Config) -> SurfaceAttributes {
+    SurfaceAttributes {
+        depth_bits: config.depth_size(),
+        stencil_bits: config.stencil_size(),
+        alpha_bits: config.alpha_size(),
+        samples: config.num_samples(),
+        srgb: config.srgb_capable(),
+        transparency: config.supports_transparency().unwrap_or(false),
+    }
+}
+fn meets_requirements(have: &SurfaceAttributes, required: &SurfaceAttributes) -> bool {
+    have.depth_bits >= required.depth_bits
+        && have.stencil_bits >= required.stencil_bits
+        && have.alpha_bits >= required.alpha_bits
+        && have.samples >= required.samples
+        && (have.srgb || !required.srgb)
+        && (have.transparency || !required.transparency)
+}
+/// Higher is better, 0 is an exact match with `preferred`.
+fn config_score(have: &SurfaceAttributes, preferred: &SurfaceAttributes) -> i32 {
+    let distance = |have: u8, want: u8| (have as i32 - want as i32).abs();
+
+    -(distance(have.depth_bits, preferred.depth_bits)
+        + distance(have.stencil_bits, preferred.stencil_bits)
+        + distance(have.alpha_bits, preferred.alpha_bits)
+        + distance(have.samples, preferred.samples) * 2
+        + if have.srgb != preferred.srgb { 8 } else { 0 }
+        + if have.transparency != preferred.transparency { 8 } else { 0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attributes(depth_bits: u8, stencil_bits: u8, samples: u8, srgb: bool) -> SurfaceAttributes {
+        SurfaceAttributes { depth_bits, stencil_bits, alpha_bits: 8, samples, srgb, transparency: false }
+    }
+
+    #[test]
+    fn requirements_are_minimums() {
+        let required = attributes(24, 8, 0, true);
+
+        assert!(meets_requirements(&attributes(24, 8, 0, true), &required));
+        assert!(meets_requirements(&attributes(32, 8, 4, true), &required));
+        assert!(!meets_requirements(&attributes(16, 8, 0, true), &required));
+        assert!(!meets_requirements(&attributes(24, 0, 0, true), &required));
+        assert!(!meets_requirements(&attributes(24, 8, 0, false), &required));
+        assert!(meets_requirements(&attributes(0, 0, 0, false), &SurfaceAttributes::default()));
+    }
+
+    #[test]
+    fn exact_match_scores_best() {
+        let preferred = attributes(24, 8, 4, false);
+
+        assert_eq!(config_score(&preferred, &preferred), 0);
+        assert!(config_score(&attributes(32, 8, 4, false), &preferred) < 0);
+        assert!(config_score(&attributes(24, 8, 4, false), &preferred) > config_score(&attributes(24, 8, 0, false), &preferred));
+    }
+
+    #[test]
+    fn flags_outweigh_small_bit_differences() {
+        let preferred = attributes(24, 8, 0, true);
+
+        assert!(config_score(&attributes(20, 8, 0, true), &preferred) > config_score(&attributes(24, 8, 0, false), &preferred));
+    }
+
+    #[test]
+    fn samples_weigh_double() {
+        let preferred = attributes(24, 8, 4, false);
+
+        assert_eq!(config_score(&attributes(24, 8, 2, false), &preferred), -4);
+        assert_eq!(config_score(&attributes(22, 8, 4, false), &preferred), -2);
+    }
 }
\ No newline at end of file
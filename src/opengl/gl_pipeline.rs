@@ -0,0 +1,191 @@
+use crate::{gl_check, renderer_core::{lg_pipeline::{BlendFactor, BlendOp, BlendState, CompareOp, CullMode, DepthState, FrontFace, PipelineState, PolygonMode, PrimitiveTopology, RasterState}, lg_vertex::VertexLayout}};
+
+use super::{gl_capabilities::{supported, GlFeature}, gl_state::GlStateCache, GlError};
+
+#[derive(Debug)]
+pub(crate) struct GlPipeline<K> {
+    program: K,
+    layout: VertexLayout,
+    topology: gl::types::GLenum,
+    state: PipelineState,
+}
+impl<K> GlPipeline<K> {
+    pub(crate) fn new(
+        program: K,
+        layout: VertexLayout,
+        topology: PrimitiveTopology,
+        state: PipelineState,
+    ) -> Self
+    {
+        Self {
+            program,
+            layout,
+            topology: topology_to_opengl(topology),
+            state,
+        }
+    }
+    pub(crate) fn program(&self) -> &K {
+        &self.program
+    }
+    pub(crate) fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+    pub(crate) fn topology(&self) -> gl::types::GLenum {
+        self.topology
+    }
+    pub(crate) fn state(&self) -> &PipelineState {
+        &self.state
+    }
+}
+
+/// Applies only the parts of `state` that differ from `previous`, everything when the previous state is unknown.
+pub(crate) fn apply_state(
+    cache: &mut GlStateCache,
+    state: &PipelineState,
+    previous: Option<&PipelineState>
+) -> Result<(), GlError>
+{
+    if previous.is_none_or(|previous| state.blend != previous.blend) {
+        apply_blend(cache, &state.blend, previous.map(|previous| &previous.blend))?;
+    }
+    if previous.is_none_or(|previous| state.depth != previous.depth) {
+        apply_depth(cache, &state.depth, previous.map(|previous| &previous.depth))?;
+    }
+    if previous.is_none_or(|previous| state.raster != previous.raster) {
+        apply_raster(cache, &state.raster, previous.map(|previous| &previous.raster))?;
+    }
+
+    Ok(())
+}
+
+/// Whether `field` of `state` differs from `previous`, unknown state always does.
+fn changed<S, T: PartialEq>(state: &S, previous: Option<&S>, field: impl Fn(&S) -> T) -> bool {
+    previous.is_none_or(|previous| field(state) != field(previous))
+}
+
+fn apply_blend(cache: &mut GlStateCache, blend: &BlendState, previous: Option<&BlendState>) -> Result<(), GlError> {
+    if changed(blend, previous, |blend| blend.enabled) {
+        cache.set_cap(gl::BLEND, blend.enabled)?;
+    }
+    if changed(blend, previous, |blend| (blend.src_color, blend.dst_color, blend.src_alpha, blend.dst_alpha)) {
+        gl_check!(
+            gl::BlendFuncSeparate(
+                blend_factor_to_opengl(blend.src_color),
+                blend_factor_to_opengl(blend.dst_color),
+                blend_factor_to_opengl(blend.src_alpha),
+                blend_factor_to_opengl(blend.dst_alpha),
+            ),
+            "Failed to set BlendFuncSeparate!"
+        )?;
+    }
+    if changed(blend, previous, |blend| (blend.color_op, blend.alpha_op)) {
+        gl_check!(
+            gl::BlendEquationSeparate(
+                blend_op_to_opengl(blend.color_op),
+                blend_op_to_opengl(blend.alpha_op),
+            ),
+            "Failed to set BlendEquationSeparate!"
+        )?;
+    }
+
+    Ok(())
+}
+fn apply_depth(cache: &mut GlStateCache, depth: &DepthState, previous: Option<&DepthState>) -> Result<(), GlError> {
+    if changed(depth, previous, |depth| depth.test) {
+        cache.set_cap(gl::DEPTH_TEST, depth.test)?;
+    }
+    if changed(depth, previous, |depth| depth.write) {
+        let mask = if depth.write { gl::TRUE } else { gl::FALSE };
+        gl_check!(gl::DepthMask(mask), "Failed to set DepthMask!")?;
+    }
+    if changed(depth, previous, |depth| depth.compare) {
+        gl_check!(gl::DepthFunc(compare_op_to_opengl(depth.compare)), "Failed to set DepthFunc!")?;
+    }
+
+    Ok(())
+}
+fn apply_raster(cache: &mut GlStateCache, raster: &RasterState, previous: Option<&RasterState>) -> Result<(), GlError> {
+    if changed(raster, previous, |raster| raster.cull_mode) {
+        match raster.cull_mode {
+            CullMode::NONE => cache.set_cap(gl::CULL_FACE, false)?,
+            mode => {
+                // The cap is cached, so enabling it again is free.
+                cache.set_cap(gl::CULL_FACE, true)?;
+                gl_check!(gl::CullFace(cull_mode_to_opengl(mode)), "Failed to set CullFace!")?;
+            }
+        }
+    }
+    if changed(raster, previous, |raster| raster.front_face) {
+        let face = match raster.front_face {
+            FrontFace::COUNTER_CLOCKWISE => gl::CCW,
+            FrontFace::CLOCKWISE => gl::CW,
+        };
+        gl_check!(gl::FrontFace(face), "Failed to set FrontFace!")?;
+    }
+    if changed(raster, previous, |raster| raster.polygon_mode) {
+        let mode = match raster.polygon_mode {
+            PolygonMode::FILL => gl::FILL,
+            PolygonMode::LINE => gl::LINE,
+            PolygonMode::POINT => gl::POINT,
+        };
+        if !supported(GlFeature::POLYGON_MODE) {
+            return Err(GlError::Error("Unsupported polygon mode".to_string(), "GLES can only fill polygons!".to_string()));
+        }
+        gl_check!(gl::PolygonMode(gl::FRONT_AND_BACK, mode), "Failed to set PolygonMode!")?;
+    }
+
+    Ok(())
+}
+
+fn topology_to_opengl(topology: PrimitiveTopology) -> gl::types::GLenum {
+    match topology {
+        PrimitiveTopology::POINT_LIST => gl::POINTS,
+        PrimitiveTopology::LINE_LIST => gl::LINES,
+        PrimitiveTopology::LINE_STRIP => gl::LINE_STRIP,
+        PrimitiveTopology::TRIANGLE_LIST => gl::TRIANGLES,
+        PrimitiveTopology::TRIANGLE_STRIP => gl::TRIANGLE_STRIP,
+        PrimitiveTopology::TRIANGLE_FAN => gl::TRIANGLE_FAN,
+    }
+}
+pub(crate) fn compare_op_to_opengl(op: CompareOp) -> gl::types::GLenum {
+    match op {
+        CompareOp::NEVER => gl::NEVER,
+        CompareOp::LESS => gl::LESS,
+        CompareOp::EQUAL => gl::EQUAL,
+        CompareOp::LESS_OR_EQUAL => gl::LEQUAL,
+        CompareOp::GREATER => gl::GREATER,
+        CompareOp::NOT_EQUAL => gl::NOTEQUAL,
+        CompareOp::GREATER_OR_EQUAL => gl::GEQUAL,
+        CompareOp::ALWAYS => gl::ALWAYS,
+    }
+}
+fn blend_factor_to_opengl(factor: BlendFactor) -> gl::types::GLenum {
+    match factor {
+        BlendFactor::ZERO => gl::ZERO,
+        BlendFactor::ONE => gl::ONE,
+        BlendFactor::SRC_COLOR => gl::SRC_COLOR,
+        BlendFactor::ONE_MINUS_SRC_COLOR => gl::ONE_MINUS_SRC_COLOR,
+        BlendFactor::DST_COLOR => gl::DST_COLOR,
+        BlendFactor::ONE_MINUS_DST_COLOR => gl::ONE_MINUS_DST_COLOR,
+        BlendFactor::SRC_ALPHA => gl::SRC_ALPHA,
+        BlendFactor::ONE_MINUS_SRC_ALPHA => gl::ONE_MINUS_SRC_ALPHA,
+        BlendFactor::DST_ALPHA => gl::DST_ALPHA,
+        BlendFactor::ONE_MINUS_DST_ALPHA => gl::ONE_MINUS_DST_ALPHA,
+    }
+}
+fn blend_op_to_opengl(op: BlendOp) -> gl::types::GLenum {
+    match op {
+        BlendOp::ADD => gl::FUNC_ADD,
+        BlendOp::SUBTRACT => gl::FUNC_SUBTRACT,
+        BlendOp::REVERSE_SUBTRACT => gl::FUNC_REVERSE_SUBTRACT,
+        BlendOp::MIN => gl::MIN,
+        BlendOp::MAX => gl::MAX,
+    }
+}
+fn cull_mode_to_opengl(mode: CullMode) -> gl::types::GLenum {
+    match mode {
+        CullMode::FRONT => gl::FRONT,
+        CullMode::FRONT_AND_BACK => gl::FRONT_AND_BACK,
+        _ => gl::BACK,
+    }
+}
\ No newline at end of file
@@ -1,13 +1,15 @@
-use std::ffi::CString;
+use std::{collections::{HashMap, HashSet}, ffi::CString};
 
 use crate::{gl_check, StdError};
 
-use super::GlError;
+use super::{gl_capabilities::{supported, GlFeature}, GlError};
 
 #[derive(Debug, Default)]
 pub struct GlProgram {
     id: gl::types::GLuint,
     pub shaders: Vec<gl::types::GLuint>,
+    /// Texture unit of every sampler uniform by name, filled after linking.
+    samplers: HashMap<String, u32>,
 }
 impl GlProgram {
     pub(crate) fn new() -> Result<Self, GlError> {
@@ -16,7 +18,8 @@ impl GlProgram {
 
         Ok(Self {
             id,
-            shaders: Vec::new()
+            shaders: Vec::new(),
+            samplers: HashMap::new(),
         })
     }
     pub(crate) fn id(&self) -> gl::types::GLuint {
@@ -39,12 +42,6 @@ impl GlProgram {
         self.shaders.push(shader);
         Ok(())
     }
-    pub(crate) fn use_prog(&self) -> Result<(), GlError> {
-        gl_check!(gl::UseProgram(self.id), "Failed to use shader program!")
-    }
-    pub(crate) fn unuse(&self) -> Result<(), GlError> {
-        gl_check!(gl::UseProgram(0), "Failed to unuse shader program!")
-    }
     pub(crate) fn get_attrib_location(&self, attrib: &str) -> Result<gl::types::GLuint, StdError>
     {
         let attrib = CString::new(attrib)?;
@@ -53,12 +50,168 @@ impl GlProgram {
             
         Ok(location)
     }
-    pub(crate) fn link(&self) -> Result<(), GlError>{
-        gl_check!(gl::LinkProgram(self.id), "Failed to link shader program!")
+    pub(crate) fn link(&mut self) -> Result<(), GlError>{
+        gl_check!(gl::LinkProgram(self.id), "Failed to link shader program!")?;
+        self.reflect_samplers()
+    }
+    /// Texture unit the sampler uniform `name` reads from.
+    pub(crate) fn sampler_unit(&self, name: &str) -> Option<u32> {
+        self.samplers.get(name).copied()
+    }
+    /// Reads the unit of every sampler uniform, `layout(binding = N)` sets it in the shader.
+    /// Samplers left sharing a unit, usually because none was declared, are moved to free units.
+    fn reflect_samplers(&mut self) -> Result<(), GlError> {
+        let mut count = 0;
+        gl_check!(gl::GetProgramiv(self.id, gl::ACTIVE_UNIFORMS, &mut count), "Failed to get active uniforms!")?;
+
+        let mut found = Vec::new();
+        for index in 0..count.max(0) as gl::types::GLuint {
+            let mut name = [0u8; 256];
+            let (mut length, mut size, mut ty) = (0, 0, 0);
+            gl_check!(
+                gl::GetActiveUniform(self.id, index, name.len() as i32, &mut length, &mut size, &mut ty, name.as_mut_ptr() as *mut _),
+                "Failed to get active uniform!"
+            )?;
+            if !is_sampler_type(ty) {
+                continue;
+            }
+
+            // Arrays report their first element and their length, `layout(binding = N)` gives element i unit N + i.
+            let name = String::from_utf8_lossy(&name[..length as usize]);
+            let name = name.strip_suffix("[0]").unwrap_or(&name).to_string();
+            let size = size.max(1) as u32;
+
+            let mut units = Vec::with_capacity(size as usize);
+            let mut location = -1;
+            for element in 0..size {
+                let element_name = if size > 1 { format!("{}[{}]", name, element) } else { name.clone() };
+                let element_name = CString::new(element_name).unwrap();
+                let element_location;
+                gl_check!(element_location = gl::GetUniformLocation(self.id, element_name.as_ptr()), "Failed to get uniform location!")?;
+                let mut unit = 0;
+                gl_check!(gl::GetUniformiv(self.id, element_location, &mut unit), "Failed to get sampler unit!")?;
+
+                if element == 0 {
+                    location = element_location;
+                }
+                units.push(unit as u32);
+            }
+            // Only a run of consecutive units can be kept as is.
+            let consecutive = units.iter().enumerate().all(|(element, unit)| *unit == units[0] + element as u32);
+            found.push((name, location, units[0], size, consecutive));
+        }
+
+        let mut used = HashSet::new();
+        let mut reassign = Vec::new();
+        for (name, location, unit, size, consecutive) in found {
+            if consecutive && (unit..unit + size).all(|unit| !used.contains(&unit)) {
+                used.extend(unit..unit + size);
+                self.insert_sampler(name, unit, size);
+            } else {
+                reassign.push((name, location, size));
+            }
+        }
+        for (name, location, size) in reassign {
+            let unit = free_units(&used, size);
+            used.extend(unit..unit + size);
+            self.set_sampler_units(location, unit, size)?;
+            self.insert_sampler(name, unit, size);
+        }
+
+        Ok(())
+    }
+    /// Arrays are also registered per element, as `name[i]`.
+    fn insert_sampler(&mut self, name: String, unit: u32, size: u32) {
+        if size > 1 {
+            for element in 0..size {
+                self.samplers.insert(format!("{}[{}]", name, element), unit + element);
+            }
+        }
+        self.samplers.insert(name, unit);
+    }
+    /// Points `count` consecutive sampler uniforms, starting at `location`, to the units from `unit` on.
+    fn set_sampler_units(&self, location: gl::types::GLint, unit: u32, count: u32) -> Result<(), GlError> {
+        let units: Vec<i32> = (unit..unit + count).map(|unit| unit as i32).collect();
+        if supported(GlFeature::PROGRAM_UNIFORMS) {
+            return gl_check!(
+                gl::ProgramUniform1iv(self.id, location, count as i32, units.as_ptr()),
+                "Failed to set sampler unit!"
+            );
+        }
+
+        // Restores the previous program so the renderer's state cache stays valid.
+        let mut previous = 0;
+        gl_check!(gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut previous), "Failed to get current program!")?;
+        gl_check!(gl::UseProgram(self.id), "Failed to use shader program!")?;
+        gl_check!(gl::Uniform1iv(location, count as i32, units.as_ptr()), "Failed to set sampler unit!")?;
+        gl_check!(gl::UseProgram(previous as gl::types::GLuint), "Failed to use shader program!")
     }
 }
 impl Drop for GlProgram {
     fn drop(&mut self) {
         gl_check!(gl::DeleteProgram(self.id), "Failed do delete shader program!").unwrap();
     }
-}
\ No newline at end of file
+}
+
+/// First unit of the lowest run of `count` units none of which is in `used`.
+fn free_units(used: &HashSet<u32>, count: u32) -> u32 {
+    (0..).find(|start| (*start..start + count).all(|unit| !used.contains(&unit))).unwrap()
+}
+fn is_sampler_type(ty: gl::types::GLenum) -> bool {
+    matches!(
+        ty,
+        gl::SAMPLER_1D
+            | gl::SAMPLER_2D
+            | gl::SAMPLER_3D
+            | gl::SAMPLER_CUBE
+            | gl::SAMPLER_1D_SHADOW
+            | gl::SAMPLER_2D_SHADOW
+            | gl::SAMPLER_1D_ARRAY
+            | gl::SAMPLER_2D_ARRAY
+            | gl::SAMPLER_1D_ARRAY_SHADOW
+            | gl::SAMPLER_2D_ARRAY_SHADOW
+            | gl::SAMPLER_CUBE_SHADOW
+            | gl::SAMPLER_CUBE_MAP_ARRAY
+            | gl::SAMPLER_CUBE_MAP_ARRAY_SHADOW
+            | gl::SAMPLER_2D_RECT
+            | gl::SAMPLER_2D_RECT_SHADOW
+            | gl::SAMPLER_BUFFER
+            | gl::SAMPLER_2D_MULTISAMPLE
+            | gl::SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::INT_SAMPLER_1D
+            | gl::INT_SAMPLER_2D
+            | gl::INT_SAMPLER_3D
+            | gl::INT_SAMPLER_CUBE
+            | gl::INT_SAMPLER_1D_ARRAY
+            | gl::INT_SAMPLER_2D_ARRAY
+            | gl::INT_SAMPLER_CUBE_MAP_ARRAY
+            | gl::INT_SAMPLER_2D_RECT
+            | gl::INT_SAMPLER_BUFFER
+            | gl::INT_SAMPLER_2D_MULTISAMPLE
+            | gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_1D
+            | gl::UNSIGNED_INT_SAMPLER_2D
+            | gl::UNSIGNED_INT_SAMPLER_3D
+            | gl::UNSIGNED_INT_SAMPLER_CUBE
+            | gl::UNSIGNED_INT_SAMPLER_1D_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_2D_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_CUBE_MAP_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_2D_RECT
+            | gl::UNSIGNED_INT_SAMPLER_BUFFER
+            | gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE
+            | gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY
+    )
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_units_fit_whole_arrays() {
+        let used: HashSet<u32> = [0, 1, 3, 6].into_iter().collect();
+        assert_eq!(free_units(&used, 1), 2);
+        assert_eq!(free_units(&used, 2), 4);
+        assert_eq!(free_units(&used, 3), 7);
+        assert_eq!(free_units(&HashSet::new(), 4), 0);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::gl_check;
+
+use super::{gl_buffer::GlBuffer, gl_capabilities::dsa, gl_state::GlStateCache, GlError};
+
+/// Waits up to `timeout` nanoseconds, returns whether the fence was signaled.
+pub(crate) fn wait_sync(fence: gl::types::GLsync, timeout: u64) -> Result<bool, GlError> {
+    let status;
+    gl_check!(status = gl::ClientWaitSync(fence, gl::SYNC_FLUSH_COMMANDS_BIT, timeout), "Failed to wait for fence!")?;
+
+    match status {
+        gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => Ok(true),
+        gl::TIMEOUT_EXPIRED => Ok(false),
+        _ => Err(GlError::Error("Fence wait failed".to_string(), "glClientWaitSync returned GL_WAIT_FAILED!".to_string())),
+    }
+}
+
+/// Staging buffer the GPU copies into, fenced so the copy can be picked up without stalling.
+#[derive(Debug)]
+pub(crate) struct GlReadback {
+    buffer: GlBuffer,
+    size: usize,
+    fence: gl::types::GLsync,
+}
+impl GlReadback {
+    /// Leaves the staging buffer bound to `GL_PIXEL_PACK_BUFFER`, pixel reads write there even with DSA.
+    pub(crate) fn new(size: usize, state_cache: &mut GlStateCache) -> Result<Self, GlError> {
+        let buffer = GlBuffer::new(gl::PIXEL_PACK_BUFFER)?;
+        state_cache.bind_buffer(gl::PIXEL_PACK_BUFFER, buffer.id())?;
+        buffer.set_data_full(size, std::ptr::null(), gl::STREAM_READ)?;
+
+        Ok(Self {
+            buffer,
+            size,
+            fence: std::ptr::null(),
+        })
+    }
+    pub(crate) fn buffer(&self) -> &GlBuffer {
+        &self.buffer
+    }
+    /// Has to follow the commands that fill the buffer.
+    pub(crate) fn fence(&mut self) -> Result<(), GlError> {
+        gl_check!(self.fence = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0), "Failed to create fence!")
+    }
+    pub(crate) fn poll(&self) -> Result<bool, GlError> {
+        wait_sync(self.fence, 0)
+    }
+    pub(crate) fn wait(&self) -> Result<(), GlError> {
+        while !wait_sync(self.fence, 1_000_000_000)? {}
+
+        Ok(())
+    }
+    /// Only blocks if the fence wasn't signaled yet.
+    pub(crate) fn read(&self, state_cache: &mut GlStateCache) -> Result<Vec<u8>, GlError> {
+        self.wait()?;
+        if !dsa() {
+            state_cache.bind_buffer(gl::PIXEL_PACK_BUFFER, self.buffer.id())?;
+        }
+
+        let mut data = vec![0; self.size];
+        self.buffer.get_sub_data(0, &mut data)?;
+
+        Ok(data)
+    }
+}
+impl Drop for GlReadback {
+    fn drop(&mut self) {
+        if !self.fence.is_null() {
+            gl_check!(gl::DeleteSync(self.fence), "Failed to delete fence!").unwrap();
+        }
+    }
+}
\ No newline at end of file
@@ -1,34 +1,61 @@
-use std::{ffi::CString, hash::Hash};
+use std::{collections::HashMap, ffi::CString, hash::Hash, ops::Range};
 
 use glutin::{display::GlDisplay, surface::GlSurface};
 use sllog::error;
-use crate::{gl_check, renderer_core::{lg_shader::LgShader, lg_texture::LgTexture, lg_uniform::LgUniform, lg_vertex::GlVertex, GraphicsApi}, StdError};
-use super::{gl_buffer::GlBuffer, gl_program::GlProgram, gl_storage::GlStorage, gl_vertex_array::GlVertexArray, GlError, GlSpecs};
+use crate::{gl_check, renderer_core::{lg_atlas::TextureAtlas, lg_buffer::{BufferUsage, Pod, ReadbackHandle}, lg_image::RgbaImage, lg_pipeline::{PipelineDescriptor, PipelineState}, lg_sampler::SamplerDescriptor, lg_shader::LgShader, lg_texture::{LgTexture, TextureRegion}, lg_uniform::{ImageAccess, ImageBinding, LgUniform, LgUniformType, UniformBinding}, lg_vertex::{GlVertex, VertexLayout, VertexStep, MAX_VERTEX_STREAMS}, GlApi, GraphicsApi}, StdError};
+use super::{gl_bindless::{bindless, load_bindless}, gl_buffer::usage_to_opengl, gl_capabilities::{dsa, GlCapabilities}, gl_pipeline::apply_state, gl_readback::GlReadback, gl_ring_buffer::GlRingBuffer, gl_uniform_arena::GlUniformArena, gl_state::{memory_barrier, GlStateCache, GlStateCounters}, gl_storage::GlStorage, gl_texture::tex_internal_format_to_opengl, gl_vertex_array::GlVertexArray, GlError, GlSpecs};
 
 struct RendererConfig {
     v_sync: bool,
 }
 
-#[derive(Default)]
-struct DrawData {
-    program: Option<*const GlProgram>, // This is safe since I will not be modifying the storage, and if I do then the pointers will be updated
-    vao: Option<*const GlVertexArray>,
+/// Program and pipeline are kept as keys since creating others can move them inside the storage.
+struct DrawData<K> {
+    program: Option<K>,
+    pipeline: Option<K>,
+    vao: Option<*mut GlVertexArray>,
     vao_set: bool,
     indices_len: Option<i32>,
-    instance_first_location: u32,
+}
+impl<K> Default for DrawData<K> {
+    fn default() -> Self {
+        Self {
+            program: None,
+            pipeline: None,
+            vao: None,
+            vao_set: false,
+            indices_len: None,
+        }
+    }
 }
 pub struct GlRenderer<K: Eq + PartialEq + Hash> {
-    instance_vbo: GlBuffer,
+    ring: GlRingBuffer,
+    uniform_arena: GlUniformArena<K>,
+    readbacks: HashMap<u64, GlReadback>,
+    next_readback: u64,
     storage: GlStorage<K>,
     specs: GlSpecs,
+    capabilities: GlCapabilities,
     config: RendererConfig,
     
-    draw_data: DrawData,
+    /// `None` after applying a pipeline failed partway, the next one is applied in full.
+    pipeline_state: Option<PipelineState>,
+    state_cache: GlStateCache,
+    draw_data: DrawData<K>,
 }
 impl<K: Eq + PartialEq + Hash> GlRenderer<K> {
     pub fn get_specs(&self) -> &GlSpecs {
         &self.specs
     }
+    pub fn capabilities(&self) -> &GlCapabilities {
+        &self.capabilities
+    }
+    pub fn state_counters(&self) -> &GlStateCounters {
+        self.state_cache.counters()
+    }
+    pub fn reset_state_counters(&mut self) {
+        self.state_cache.reset_counters();
+    }
 }
 impl<K: Eq + PartialEq + Hash> GlRenderer<K> {
     pub(crate) fn set_vsync(&mut self, v_sync: bool) {
@@ -44,168 +71,528 @@ impl<K: Eq + PartialEq + Hash> GlRenderer<K> {
     }
 }
 impl<K: Eq + PartialEq + Hash + Default + Clone> GlRenderer<K> {
+    /// The last pipeline's blend, depth and raster state stays applied.
     pub(crate) fn set_program<S: LgShader>(&mut self, shaders: (K, &[(K, &S)])) -> Result<(), GlError> {
-        let program = self.storage.set_program(shaders.0, shaders.1);
+        let program = self.storage.set_program(shaders.0.clone(), shaders.1);
         
-        program.use_prog()?;
-        self.draw_data.program = Some(program as *const GlProgram);
+        self.state_cache.use_program(program.id())?;
+        self.draw_data.program = Some(shaders.0);
+        self.draw_data.pipeline = None;
         
         Ok(())
     }
     
+    /// Replaces a pipeline created with the same key, reapplying it if it's the current one.
+    pub(crate) fn create_pipeline<S: LgShader>(&mut self, key: K, descriptor: PipelineDescriptor<K, S>) -> Result<(), StdError> {
+        self.storage.set_pipeline(key.clone(), descriptor);
+        if self.draw_data.pipeline.as_ref() == Some(&key) {
+            self.set_pipeline(key)?;
+        }
+        
+        Ok(())
+    }
+
+    pub(crate) fn set_pipeline(&mut self, key: K) -> Result<(), StdError> {
+        let pipeline = self.storage.pipelines.get(&key).ok_or("Failed to get Pipeline! (OpenGL)")?;
+        let program = self.storage.programs.get(pipeline.program()).ok_or("Failed to get Program! (OpenGL)")?;
+        
+        self.state_cache.use_program(program.id())?;
+        if let Err(e) = apply_state(&mut self.state_cache, pipeline.state(), self.pipeline_state.as_ref()) {
+            self.pipeline_state = None;
+            return Err(e.into());
+        }
+        self.pipeline_state = Some(*pipeline.state());
+
+        self.draw_data.program = Some(pipeline.program().clone());
+        self.draw_data.pipeline = Some(key);
+        
+        Ok(())
+    }
+    
+    /// A vao that already has indices can be drawn right away.
     pub(crate) fn set_vao(&mut self, id: K) -> Result<(), GlError> {
         let (present, vao) = self.storage.set_vao(id);
-        vao.bind()?;
+        self.state_cache.bind_vertex_array(vao.id())?;
 
         self.draw_data.vao_set = present;
-        self.draw_data.vao = Some(vao as *const GlVertexArray);
+        self.draw_data.indices_len = Some(vao.index_count() as i32).filter(|_| present && vao.index_count() > 0);
+        self.draw_data.vao = Some(vao as *mut GlVertexArray);
         
         Ok(())
     }
     
-    pub(crate) fn set_vertices<V: GlVertex>(&mut self, vertices: &[V]) -> Result<(), StdError> {
-        if let Some(vao) = &self.draw_data.vao {
-            let vao = unsafe { &**vao };
-            vao.vertex_buffer().bind()?;
+    /// Only stream 0 is checked against the pipeline's vertex layout.
+    pub(crate) fn set_vertex_stream<V: GlVertex>(&mut self, stream: u32, step: VertexStep, vertices: &[V]) -> Result<(), StdError> {
+        let layout = self.stream_layout::<V>(stream)?;
+        self.upload_stream(stream, &layout, step.divisor(), vertices, gl::STATIC_DRAW, false)
+    }
 
-            let layout = unsafe { V::gl_info() };
-            self.draw_data.instance_first_location = layout.last().ok_or("Failed to get last location! (OpenGL)")?.0;
+    /// Replaces the stream's data and layout, creating the stream if needed.
+    pub(crate) fn replace_vertex_stream<V: GlVertex>(
+        &mut self,
+        stream: u32,
+        step: VertexStep,
+        vertices: &[V],
+        usage: BufferUsage
+    ) -> Result<(), StdError> 
+    {
+        let layout = self.stream_layout::<V>(stream)?;
+        self.upload_stream(stream, &layout, step.divisor(), vertices, usage_to_opengl(usage), true)
+    }
 
-            if !self.draw_data.vao_set {
-                let stride = std::mem::size_of::<V>();
+    /// `first` counts vertices, writing past the end grows the stream.
+    pub(crate) fn update_vertex_stream<V: GlVertex>(&mut self, stream: u32, first: usize, vertices: &[V]) -> Result<(), StdError> {
+        let vao = self.draw_data.vao.ok_or("Trying to update vertices without having set vao! (GlRenderer)")?;
+        let vao = unsafe { &mut *vao };
+        
+        let layout = vao.stream_layout(stream).ok_or("Trying to update a vertex stream that wasn't set! (GlRenderer)")?;
+        if layout.stride != std::mem::size_of::<V>() {
+            return Err("Vertex type doesn't match the stream's vertex layout! (GlRenderer)".into());
+        }
+        if !dsa() {
+            let buffer = vao.stream_buffer(stream).ok_or("Failed to get vertex stream! (OpenGL)")?;
+            self.state_cache.bind_buffer(gl::ARRAY_BUFFER, buffer.id())?;
+        }
+        vao.write_stream(stream, first * std::mem::size_of::<V>(), vertices, &mut self.state_cache)?;
 
-                vao.vertex_buffer().set_data(vertices, gl::STATIC_DRAW)?;
-                for info in layout {
-                    vao.set_attribute(info.0, info.1, stride, info.2)?;
-                }
-            }
-            
-            Ok(())
-        } else {
-            Err("Trying to set vertices without having set vao! (GlRenderer)".into())
+        Ok(())
+    }
+
+    /// Vertices only live until the end of the frame, the stream reads them straight from the ring buffer.
+    pub(crate) fn set_transient_vertices<V: GlVertex>(&mut self, stream: u32, step: VertexStep, vertices: &[V]) -> Result<(), StdError> {
+        let layout = self.stream_layout::<V>(stream)?;
+        self.stream_transient(stream, &layout, step.divisor(), vertices)
+    }
+
+    pub(crate) fn set_vertex_stream_enabled(&mut self, stream: u32, enabled: bool) -> Result<(), StdError> {
+        let vao = self.draw_data.vao.ok_or("Trying to enable a vertex stream without having set vao! (GlRenderer)")?;
+        unsafe { (*vao).set_stream_enabled(stream, enabled)? };
+
+        Ok(())
+    }
+
+    fn stream_layout<V: GlVertex>(&self, stream: u32) -> Result<VertexLayout, StdError> {
+        if stream >= MAX_VERTEX_STREAMS {
+            return Err(format!("Vertex stream {} is out of range, there are {} streams! (GlRenderer)", stream, MAX_VERTEX_STREAMS).into());
+        }
+        let pipeline = self.draw_data.pipeline.as_ref().and_then(|key| self.storage.pipelines.get(key));
+        let layout = match pipeline {
+            Some(pipeline) if stream == 0 => pipeline.layout().clone(),
+            _ => VertexLayout::of::<V>(),
+        };
+        if layout.stride != std::mem::size_of::<V>() {
+            return Err("Vertex type doesn't match the pipeline's vertex layout! (GlRenderer)".into());
+        }
+
+        Ok(layout)
+    }
+
+    fn stream_transient<V>(&mut self, stream: u32, layout: &VertexLayout, divisor: u32, vertices: &[V]) -> Result<(), StdError> {
+        let vao = self.draw_data.vao.ok_or("Trying to set vertices without having set vao! (GlRenderer)")?;
+        let vao = unsafe { &mut *vao };
+
+        let (_, bytes, _) = unsafe { vertices.align_to::<u8>() };
+        let allocation = self.ring.allocate(bytes, std::mem::align_of::<V>().max(4), &mut self.state_cache)?;
+        if !dsa() {
+            self.state_cache.bind_buffer(gl::ARRAY_BUFFER, self.ring.id())?;
+        }
+        vao.set_stream_range(stream, self.ring.id(), allocation.offset, layout, divisor)?;
+
+        Ok(())
+    }
+
+    /// Static streams are only uploaded when they're created, unless `always` is set.
+    fn upload_stream<V>(
+        &mut self,
+        stream: u32,
+        layout: &VertexLayout,
+        divisor: u32,
+        vertices: &[V],
+        usage: gl::types::GLenum,
+        always: bool
+    ) -> Result<(), StdError> 
+    {
+        let vao = self.draw_data.vao.ok_or("Trying to set vertices without having set vao! (GlRenderer)")?;
+        let vao = unsafe { &mut *vao };
+        
+        let (present, buffer) = vao.create_stream(stream)?;
+        if !dsa() {
+            self.state_cache.bind_buffer(gl::ARRAY_BUFFER, buffer.id())?;
         }
+        if always || !present {
+            vao.replace_stream(stream, vertices, usage)?;
+            vao.set_stream_layout(stream, layout, divisor)?;
+        }
+
+        Ok(())
     }
 
     pub(crate) fn set_indices(&mut self, indices: &[u32]) -> Result<(), StdError> {
-        if let Some(vao) = &self.draw_data.vao {
-            let vao = unsafe { &**vao };
-            vao.index_buffer().bind()?;
+        if self.draw_data.vao_set {
             self.draw_data.indices_len = Some(indices.len() as i32);
+            return Ok(());
+        }
 
-            if !self.draw_data.vao_set {
-                vao.index_buffer().set_data(indices, gl::STATIC_DRAW)?;
-            }
-            
-            Ok(())
-        } else {
-            Err("Trying to set indices without having set vertices! (GlRenderer)".into())
+        self.replace_indices(indices, BufferUsage::STATIC)
+    }
+
+    pub(crate) fn replace_indices(&mut self, indices: &[u32], usage: BufferUsage) -> Result<(), StdError> {
+        let vao = self.draw_data.vao.ok_or("Trying to set indices without having set vao! (GlRenderer)")?;
+        let vao = unsafe { &mut *vao };
+        
+        if !dsa() {
+            self.state_cache.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, vao.index_buffer().id())?;
         }
+        vao.replace_indices(indices, usage_to_opengl(usage))?;
+        self.draw_data.indices_len = Some(vao.index_count() as i32);
+
+        Ok(())
+    }
+
+    /// Writing past the end grows the index buffer and the index count.
+    pub(crate) fn update_indices(&mut self, first: usize, indices: &[u32]) -> Result<(), StdError> {
+        let vao = self.draw_data.vao.ok_or("Trying to update indices without having set vao! (GlRenderer)")?;
+        let vao = unsafe { &mut *vao };
+        
+        if !dsa() {
+            self.state_cache.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, vao.index_buffer().id())?;
+        }
+        vao.write_indices(first, indices, &mut self.state_cache)?;
+        self.draw_data.indices_len = Some(vao.index_count() as i32);
+
+        Ok(())
     }
     
     pub(crate) fn set_uniforms(&mut self, ubos: Vec<(K, &impl LgUniform)>) -> Result<(), StdError> {
-        self.storage.set_uniforms(&ubos);
-        
         for (key, uniform) in ubos {
-            let ubo = self.storage.buffers.get(&key).ok_or("Failed to get UBO! (OpenGL)")?;
+            match uniform.u_type() {
+                LgUniformType::STORAGE_IMAGE => return Err("Storage images have to be bound with set_images! (OpenGL)".into()),
+                LgUniformType::COMBINED_IMAGE_SAMPLER => return Err("Combined image samplers have to be bound with set_textures! (OpenGL)".into()),
+                _ => (),
+            }
+            let (present, ubo) = self.storage.set_uniform(key, uniform);
             
-            ubo.bind()?;
-            ubo.bind_base(uniform.binding())?;
-            if uniform.update_data() {
+            if !present || uniform.update_data() {
+                if !dsa() {
+                    self.state_cache.bind_buffer(ubo.target(), ubo.id())?;
+                }
                 ubo.set_data_full(
                     uniform.data_size(), 
                     uniform.get_raw_data(), 
                     gl::STATIC_DRAW
                 )?;
             }
-            ubo.unbind()?;
+            self.state_cache.bind_buffer_base(ubo.target(), uniform.binding() as gl::types::GLuint, ubo.id())?;
+        }
+
+        Ok(())
+    }
+    
+    /// Like `set_uniforms` but every key gets an aligned range of a shared arena buffer instead of its own buffer.
+    pub(crate) fn set_arena_uniforms(&mut self, ubos: Vec<(K, &impl LgUniform)>) -> Result<(), StdError> {
+        for (key, uniform) in ubos {
+            let (target, align) = self.block_target(uniform.u_type())?;
+            let (present, slot) = self.uniform_arena.slot(key, uniform.data_size(), align, &mut self.state_cache)?;
+            let page = self.uniform_arena.page(slot.page);
+
+            if !present || uniform.update_data() {
+                if !dsa() {
+                    self.state_cache.bind_buffer(page.target(), page.id())?;
+                }
+                let bytes = unsafe { std::slice::from_raw_parts(uniform.get_raw_data() as *const u8, uniform.data_size()) };
+                page.sub_data(slot.offset, bytes)?;
+            }
+            self.state_cache.bind_buffer_range(target, uniform.binding() as gl::types::GLuint, page.id(), slot.offset, slot.size)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn release_arena_uniform(&mut self, key: K) -> Result<(), StdError> {
+        if !self.uniform_arena.release(&key) {
+            return Err("Failed to get arena uniform! (OpenGL)".into());
+        }
+
+        Ok(())
+    }
+
+    /// Binding target and offset alignment of a uniform or storage block.
+    fn block_target(&self, u_type: LgUniformType) -> Result<(gl::types::GLenum, usize), StdError> {
+        match u_type {
+            LgUniformType::STRUCT => Ok((gl::UNIFORM_BUFFER, self.capabilities.uniform_buffer_offset_alignment)),
+            LgUniformType::STORAGE_BUFFER if self.capabilities.storage_buffer_offset_alignment != 0 => {
+                Ok((gl::SHADER_STORAGE_BUFFER, self.capabilities.storage_buffer_offset_alignment))
+            },
+            LgUniformType::STORAGE_BUFFER => Err("Shader storage buffers aren't supported! (OpenGL)".into()),
+            _ => Err("Only uniform and storage blocks can be bound as buffer ranges! (OpenGL)".into()),
+        }
+    }
+
+    /// Uniform and storage blocks that only live until the end of the frame, bound as ranges of the ring buffer.
+    pub(crate) fn set_transient_uniforms(&mut self, uniforms: &[&impl LgUniform]) -> Result<(), StdError> {
+        for uniform in uniforms {
+            let (target, align) = self.block_target(uniform.u_type())?;
+
+            let bytes = unsafe { std::slice::from_raw_parts(uniform.get_raw_data() as *const u8, uniform.data_size()) };
+            let allocation = self.ring.allocate(bytes, align, &mut self.state_cache)?;
+            self.state_cache.bind_buffer_range(
+                target,
+                uniform.binding() as gl::types::GLuint,
+                self.ring.id(),
+                allocation.offset,
+                allocation.size
+            )?;
         }
 
         Ok(())
     }
     
-    pub(crate) fn set_textures<T: LgTexture>(&mut self, textures: &[(K, &T, u32)]) -> Result<(), StdError> {
+    pub(crate) fn set_textures<T: LgTexture>(
+        &mut self,
+        textures: &[(K, &T, UniformBinding, Option<&SamplerDescriptor>)]
+    ) -> Result<(), StdError>
+    {
+        let default_sampler = SamplerDescriptor::default();
+        
         for tex in textures {
-            self.storage.set_texture(tex.0.clone(), tex.1, tex.2);
-            self.storage.textures.get(&tex.0).ok_or("Failed to get Texture! (OpenGL)")?.bind(tex.2)?;
-            gl_check!(gl::Uniform1i(tex.2 as i32, tex.2 as i32), "Failed to send Texture to Shader!")?;
+            let unit = self.texture_unit(tex.2)?;
+            if let Some(compressed) = tex.1.compression() {
+                if !self.capabilities.supports_compressed(compressed) {
+                    return Err(format!("{:?} isn't supported by the context! (OpenGL)", compressed).into());
+                }
+            }
+            self.load_texture(tex.0.clone(), tex.1, unit)?;
+            let gl_tex = &self.storage.textures[&tex.0];
+            self.state_cache.image_barrier(gl_tex.id())?;
+            self.state_cache.bind_texture(unit, gl_tex.target(), gl_tex.id())?;
+
+            let sampler = self.storage.set_sampler(tex.3.unwrap_or(&default_sampler), self.capabilities.max_anisotropy)?;
+            self.state_cache.bind_sampler(unit, sampler.id())?;
         }
         
         Ok(())
     }
+    /// Loads or reallocates the texture, without DSA this binds it to `unit` behind the cache's back.
+    fn load_texture<T: LgTexture>(&mut self, key: K, texture: &T, unit: u32) -> Result<(), StdError> {
+        if let Some(gl_tex) = self.storage.textures.get(&key) {
+            if !gl_tex.matches(texture) {
+                if gl_tex.has_handles() {
+                    return Err("Reallocating a texture with resident bindless handles, release them first! (GlRenderer)".into());
+                }
+                self.state_cache.forget_texture(gl_tex.id());
+            }
+        }
 
-    pub(crate) fn draw(&mut self) -> Result<(), StdError> {
-        if let Some(vao) = &self.draw_data.vao {
-            let vao = unsafe { &**vao };
-            
-            if let Some(indices_len) = self.draw_data.indices_len {
-                gl_check!(gl::DrawElements(
-                    gl::TRIANGLES,
-                    indices_len,
-                    gl::UNSIGNED_INT,
-                    std::ptr::null(),
-                ), "Failed to draw elements!")?;
-                let program = unsafe { &*self.draw_data.program.unwrap() };
+        let (present, _) = self.storage.set_texture(key, texture, unit)?;
+        if !present {
+            self.state_cache.invalidate_texture_unit(unit);
+        }
+
+        Ok(())
+    }
+    fn texture_unit(&self, binding: UniformBinding) -> Result<u32, StdError> {
+        match binding {
+            UniformBinding::INDEX(unit) => Ok(unit),
+            UniformBinding::NAME(name) => {
+                let key = self.draw_data.program.as_ref().ok_or("Trying to bind a texture by name without having set a program! (GlRenderer)")?;
+                let program = self.storage.programs.get(key).ok_or("Failed to get Program! (OpenGL)")?;
                 
-                self.instance_vbo.unbind()?;
-                vao.vertex_buffer().unbind()?;
-                vao.index_buffer().unbind()?;
-                vao.unbind()?;
-                program.unuse()?;
-            } else { return Err("Failed to draw instanced: no indices! (GlRenderer)".into()); }
-            
-            self.draw_data = DrawData::default();
+                program.sampler_unit(name).ok_or_else(|| format!("No sampler uniform named {}! (OpenGL)", name).into())
+            },
+        }
+    }
 
-            Ok(())
-        } else {
-            Err("Trying to draw instanced without having set vao! (GlRenderer)".into())
+    /// (key, texture, binding), loads the texture if needed and binds it for image load/store.
+    pub(crate) fn set_images<T: LgTexture>(&mut self, images: &[(K, &T, ImageBinding)]) -> Result<(), StdError> {
+        if !self.capabilities.image_load_store {
+            return Err("Image load/store needs GL 4.2, GLES 3.1 or GL_ARB_shader_image_load_store! (GlRenderer)".into());
         }
+        for (key, texture, binding) in images {
+            self.load_texture(key.clone(), *texture, 0)?;
+            let id = self.storage.textures[key].id();
+            self.state_cache.image_barrier(id)?;
+
+            let (format, ty) = binding.format.unwrap_or((texture.texture_format(), texture.texture_type()));
+            let internal = tex_internal_format_to_opengl(format, ty)?;
+            let access = match binding.access {
+                ImageAccess::READ_ONLY => gl::READ_ONLY,
+                ImageAccess::WRITE_ONLY => gl::WRITE_ONLY,
+                ImageAccess::READ_WRITE => gl::READ_WRITE,
+            };
+
+            gl_check!(
+                gl::BindImageTexture(
+                    binding.binding,
+                    id,
+                    binding.level as i32,
+                    binding.layer.is_none() as gl::types::GLboolean,
+                    binding.layer.unwrap_or(0) as i32,
+                    access,
+                    internal
+                ),
+                "Failed to bind image texture!"
+            )?;
+            if binding.access.writes() {
+                self.state_cache.image_written(id);
+            }
+        }
+
+        Ok(())
     }
 
-    pub(crate) fn draw_instanced<V: GlVertex>(&mut self, instance_data: &[V]) -> Result<(), StdError> {
-        let layout = unsafe { V::gl_info() };
-        let stride = std::mem::size_of::<V>();
-        let instance_count = instance_data.len();
-        let last_location = self.draw_data.instance_first_location;
+    pub(crate) fn dispatch_compute(&mut self, groups_x: u32, groups_y: u32, groups_z: u32) -> Result<(), StdError> {
+        if self.draw_data.program.is_none() {
+            return Err("Trying to dispatch without having set a program! (GlRenderer)".into());
+        }
+        if !self.capabilities.compute_shader {
+            return Err("Compute shaders need GL 4.3, GLES 3.1 or GL_ARB_compute_shader! (GlRenderer)".into());
+        }
+        gl_check!(gl::DispatchCompute(groups_x, groups_y, groups_z), "Failed to dispatch compute!")?;
+
+        Ok(())
+    }
+
+    /// Loads the texture if needed and returns a resident handle for it, which shaders can use
+    /// as a sampler after reading it from a buffer.
+    pub(crate) fn texture_handle<T: LgTexture>(
+        &mut self,
+        key: K,
+        texture: &T,
+        sampler: Option<&SamplerDescriptor>
+    ) -> Result<u64, StdError>
+    {
+        if !bindless() {
+            return Err("Bindless textures aren't supported! (OpenGL)".into());
+        }
+
+        let sampler = self.storage.set_sampler(sampler.unwrap_or(&SamplerDescriptor::default()), self.capabilities.max_anisotropy)?.id();
+        self.load_texture(key.clone(), texture, 0)?;
+        let gl_tex = self.storage.textures.get_mut(&key).unwrap();
+
+        Ok(gl_tex.bindless_handle(sampler)?)
+    }
+    /// Handles of `key` stop being resident, shaders must not use them afterwards.
+    pub(crate) fn release_texture_handles(&mut self, key: K) -> Result<(), StdError> {
+        let gl_tex = self.storage.textures.get_mut(&key).ok_or("Failed to get Texture! (OpenGL)")?;
+        gl_tex.release_handles()?;
+
+        Ok(())
+    }
+
+    pub(crate) fn update_texture(&mut self, key: K, region: &TextureRegion, bytes: &[u8]) -> Result<(), StdError> {
+        let gl_tex = self.storage.textures.get(&key).ok_or("Failed to get Texture! (OpenGL)")?;
         
-        if let Some(vao) = &self.draw_data.vao {
-            let vao = unsafe { &**vao };
-            self.instance_vbo.bind()?;
-            self.instance_vbo.set_data(instance_data, gl::STATIC_DRAW)?;
-            
-            for info in layout {
-                let location = info.0 + last_location + 1;
-                vao.set_attribute(location, info.1, stride, info.2)?;
-                
-                gl_check!(gl::VertexAttribDivisor(location, 1), "Failed to set VertexAttribDivisor!")?;
+        self.state_cache.image_barrier(gl_tex.id())?;
+        if !dsa() {
+            self.state_cache.bind_texture(0, gl_tex.target(), gl_tex.id())?;
+        }
+        gl_tex.update(region, bytes)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn stream_texture(&mut self, key: K, region: &TextureRegion, bytes: &[u8]) -> Result<(), StdError> {
+        let gl_tex = self.storage.textures.get_mut(&key).ok_or("Failed to get Texture! (OpenGL)")?;
+        
+        self.state_cache.image_barrier(gl_tex.id())?;
+        if !dsa() {
+            self.state_cache.bind_texture(0, gl_tex.target(), gl_tex.id())?;
+        }
+        let stream_buffer = gl_tex.stream_buffer()?.id();
+        self.state_cache.bind_buffer(gl::PIXEL_UNPACK_BUFFER, stream_buffer)?;
+        let result = gl_tex.stream(region, bytes);
+        // Left bound, every later client side upload would read from the buffer instead.
+        self.state_cache.bind_buffer(gl::PIXEL_UNPACK_BUFFER, 0)?;
+
+        Ok(result?)
+    }
+
+    pub(crate) fn upload_atlas(&mut self, atlas: &mut TextureAtlas<K>) -> Result<(), StdError> {
+        for (key, page) in atlas.dirty_pages() {
+            let region = page.take_dirty().unwrap();
+
+            match self.storage.textures.get(key) {
+                Some(gl_tex) if gl_tex.matches(page) => {
+                    if !dsa() {
+                        self.state_cache.bind_texture(0, gl_tex.target(), gl_tex.id())?;
+                    }
+                    gl_tex.update(&region, &page.region_bytes(&region))?;
+                },
+                _ => self.load_texture(key.clone(), page, 0)?,
             }
-            
-            if let Some(indices_len) = self.draw_data.indices_len {
-                gl_check!(
-                    gl::DrawElementsInstanced(
-                        gl::TRIANGLES,
-                        indices_len,
-                        gl::UNSIGNED_INT,
-                        std::ptr::null(),
-                        instance_count as i32,
-                    ),
-                    "Failed to draw Instanced!"
-                )?;
-                let program = unsafe { &*self.draw_data.program.unwrap() };
-                
-                self.instance_vbo.unbind()?;
-                vao.vertex_buffer().unbind()?;
-                vao.index_buffer().unbind()?;
-                vao.unbind()?;
-                program.unuse()?;
-            } else { return Err("Failed to draw instanced: no indices! (GlRenderer)".into()); }
-            
-            self.draw_data = DrawData::default();
+        }
 
-            Ok(())
-        } else {
-            Err("Trying to draw instanced without having set vao! (GlRenderer)".into())
+        Ok(())
+    }
+
+    pub(crate) fn generate_mipmaps(&mut self, key: K) -> Result<(), StdError> {
+        let gl_tex = self.storage.textures.get(&key).ok_or("Failed to get Texture! (OpenGL)")?;
+        
+        self.state_cache.image_barrier(gl_tex.id())?;
+        if !dsa() {
+            self.state_cache.bind_texture(0, gl_tex.target(), gl_tex.id())?;
+        }
+        gl_tex.generate_mipmaps()?;
+
+        Ok(())
+    }
+
+    pub(crate) fn draw(&mut self) -> Result<(), StdError> {
+        self.draw_elements(None)
+    }
+
+    /// Draws with the instance streams set through `set_vertex_stream`.
+    pub(crate) fn draw_instances(&mut self, instance_count: u32) -> Result<(), StdError> {
+        self.draw_elements(Some(instance_count))
+    }
+
+    /// Instance attributes are placed after the highest location of the other streams.
+    pub(crate) fn draw_instanced<V: GlVertex>(&mut self, instance_data: &[V]) -> Result<(), StdError> {
+        let vao = self.draw_data.vao.ok_or("Trying to draw instanced without having set vao! (GlRenderer)")?;
+        let last_location = unsafe { (*vao).last_location(MAX_VERTEX_STREAMS) }.ok_or("Failed to get last location! (OpenGL)")?;
+        
+        let mut layout = VertexLayout::of::<V>();
+        for attribute in &mut layout.attributes {
+            attribute.location += last_location + 1;
         }
+        self.stream_transient(MAX_VERTEX_STREAMS, &layout, 1, instance_data)?;
         
+        self.draw_elements(Some(instance_data.len() as u32))
+    }
+
+    fn draw_elements(&mut self, instance_count: Option<u32>) -> Result<(), StdError> {
+        if self.draw_data.vao.is_none() {
+            return Err("Trying to draw without having set vao! (GlRenderer)".into());
+        }
+        let indices_len = self.draw_data.indices_len.ok_or("Failed to draw: no indices! (GlRenderer)")?;
+        let pipeline = self.draw_data.pipeline.as_ref().and_then(|key| self.storage.pipelines.get(key));
+        let topology = pipeline.map_or(gl::TRIANGLES, |pipeline| pipeline.topology());
+
+        match instance_count {
+            Some(instance_count) => gl_check!(
+                gl::DrawElementsInstanced(
+                    topology,
+                    indices_len,
+                    gl::UNSIGNED_INT,
+                    std::ptr::null(),
+                    instance_count as i32,
+                ),
+                "Failed to draw Instanced!"
+            )?,
+            None => gl_check!(gl::DrawElements(
+                topology,
+                indices_len,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+            ), "Failed to draw elements!")?,
+        }
+        
+        // The pipeline and program stay bound, the vao has to be set again.
+        self.draw_data.vao = None;
+        self.draw_data.vao_set = false;
+        self.draw_data.indices_len = None;
+
+        Ok(())
     }
 }
 impl<K: Eq + PartialEq + Hash + Default> GlRenderer<K> {
@@ -215,60 +602,201 @@ impl<K: Eq + PartialEq + Hash + Default> GlRenderer<K> {
             specs.gl_display.get_proc_address(symbol.as_c_str()).cast()
         });
         
+        let capabilities = GlCapabilities::query(specs.api)?;
+        load_bindless(&specs.gl_display, capabilities.bindless_texture);
+        let ring = GlRingBuffer::new(specs.stream_buffer_size, capabilities.buffer_storage)?;
+
         Ok(Self {
+            ring,
+            uniform_arena: GlUniformArena::default(),
+            readbacks: HashMap::new(),
+            next_readback: 0,
+            capabilities,
             specs,
             config: RendererConfig { v_sync: true },
             storage: GlStorage::default(),
-            instance_vbo: GlBuffer::new(gl::ARRAY_BUFFER)?,
             
+            pipeline_state: Some(PipelineState::default()),
+            state_cache: GlStateCache::default(),
             draw_data: DrawData::default(),
         })
     }
 
     pub(crate) fn begin(&self) -> Result<(), GlError> {
         gl_check!(gl::ClearColor(0.5, 0.1, 0.2, 1.0), "Failed to ClearColor!")?;
-        gl_check!(gl::ClearDepth(1.0), "Failed to ClearDepth!")?;
+        match self.specs.api {
+            GlApi::OPEN_GL => gl_check!(gl::ClearDepth(1.0), "Failed to ClearDepth!")?,
+            GlApi::GLES => gl_check!(gl::ClearDepthf(1.0), "Failed to ClearDepth!")?,
+        }
         gl_check!(gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT), "Failed to Clear!")
     }
     pub(crate) fn end(&mut self) -> Result<(), StdError>{
         self.specs.gl_surface.swap_buffers(&self.specs.gl_context)?;
+        self.ring.next_frame()?;
         
         Ok(())
     }
-    pub(crate) unsafe fn read_buffer<T: Clone>(&self, key: K) -> Result<T, StdError> {
-        gl_check!(gl::MemoryBarrier(gl::ALL_BARRIER_BITS), "Failed to wait for barriers!")?;
+    /// Reads the `index`th `T` of the buffer.
+    pub(crate) unsafe fn read_buffer<T: Clone>(&mut self, key: K, index: usize) -> Result<T, StdError> {
+        let size = std::mem::size_of::<T>();
+        let mut result = std::mem::MaybeUninit::<T>::uninit();
+        let bytes = std::slice::from_raw_parts_mut(result.as_mut_ptr() as *mut u8, size);
+        let range = byte_range::<T>(index, 1)?;
+        self.read_buffer_bytes(key, range.start, bytes)?;
         
-        if let Some(buffer) = self.storage.buffers.get(&key) {
-            buffer.bind()?;
-            let data = buffer.map(gl::READ_ONLY)? as *const T;
-            let result = (*data).clone();
-            buffer.unmap()?;
-            buffer.unbind()?;
-            
-            return Ok(result);
+        Ok(result.assume_init())
+    }
+    /// `range` counts elements of `T`.
+    pub(crate) fn read_buffer_slice<T: Pod>(&mut self, key: K, range: Range<usize>) -> Result<Vec<T>, StdError> {
+        let offset = byte_range::<T>(range.start, range.len())?.start;
+        let mut result = vec![T::zeroed(); range.len()];
+        self.read_buffer_bytes(key, offset, bytemuck::cast_slice_mut(&mut result))?;
+
+        Ok(result)
+    }
+    fn read_buffer_bytes(&mut self, key: K, offset: usize, bytes: &mut [u8]) -> Result<(), StdError> {
+        memory_barrier(gl::ALL_BARRIER_BITS)?;
+        
+        let buffer = self.storage.buffers.get(&key).ok_or("Couldn't find buffer! (OpenGL)")?;
+        if !dsa() {
+            self.state_cache.bind_buffer(buffer.target(), buffer.id())?;
         }
+        let size = buffer.size()?;
+        let end = offset.checked_add(bytes.len()).ok_or("Buffer read range overflows! (OpenGL)")?;
+        if end > size {
+            return Err(format!("Reading bytes {}..{} of a {} byte buffer! (OpenGL)", offset, end, size).into());
+        }
+        buffer.get_sub_data(offset, bytes)?;
+
+        Ok(())
+    }
+    /// `range` counts elements of `T`, the copy happens on the GPU and is fenced.
+    pub(crate) fn read_buffer_async<T: Pod>(&mut self, key: K, range: Range<usize>) -> Result<ReadbackHandle, StdError> {
+        let buffer = self.storage.buffers.get(&key).ok_or("Couldn't find buffer! (OpenGL)")?;
+        if !dsa() {
+            self.state_cache.bind_buffer(buffer.target(), buffer.id())?;
+            self.state_cache.bind_buffer(gl::COPY_READ_BUFFER, buffer.id())?;
+        }
+        let Range { start: offset, end } = byte_range::<T>(range.start, range.len())?;
+        let size = buffer.size()?;
+        if end > size {
+            return Err(format!("Reading bytes {}..{} of a {} byte buffer! (OpenGL)", offset, end, size).into());
+        }
+        let len = end - offset;
+        memory_barrier(gl::BUFFER_UPDATE_BARRIER_BIT)?;
+
+        let readback = GlReadback::new(len, &mut self.state_cache)?;
+        readback.buffer().copy_from(buffer, offset, len)?;
+
+        self.finish_readback(readback)
+    }
+    pub(crate) fn read_texture_async(&mut self, key: K, level: u32) -> Result<ReadbackHandle, StdError> {
+        let gl_tex = self.storage.textures.get(&key).ok_or("Failed to get Texture! (OpenGL)")?;
         
-        Err("Couldn't find buffer! (OpenGL)".into())
+        self.state_cache.image_barrier(gl_tex.id())?;
+        let readback = GlReadback::new(gl_tex.level_size(level)?, &mut self.state_cache)?;
+        if !dsa() {
+            self.state_cache.bind_texture(0, gl_tex.target(), gl_tex.id())?;
+        }
+        gl_tex.pack_level(level)?;
+
+        self.finish_readback(readback)
+    }
+    /// RGBA8 pixels of the default framebuffer, rows start at the bottom like `glReadPixels`.
+    pub(crate) fn read_pixels_async(&mut self, x: i32, y: i32, width: u32, height: u32) -> Result<ReadbackHandle, StdError> {
+        let readback = GlReadback::new(width as usize * height as usize * 4, &mut self.state_cache)?;
+        gl_check!(gl::PixelStorei(gl::PACK_ALIGNMENT, 1), "Failed to set pack alignment!")?;
+        gl_check!(
+            gl::ReadPixels(x, y, width as i32, height as i32, gl::RGBA, gl::UNSIGNED_BYTE, std::ptr::null_mut()),
+            "Failed to read pixels!"
+        )?;
+
+        self.finish_readback(readback)
+    }
+    fn finish_readback(&mut self, mut readback: GlReadback) -> Result<ReadbackHandle, StdError> {
+        readback.fence()?;
+        // Pixel reads would otherwise keep going into the staging buffer.
+        self.state_cache.bind_buffer(gl::PIXEL_PACK_BUFFER, 0)?;
+
+        let handle = ReadbackHandle(self.next_readback);
+        self.next_readback += 1;
+        self.readbacks.insert(handle.0, readback);
+
+        Ok(handle)
+    }
+    pub(crate) fn poll_readback(&self, handle: ReadbackHandle) -> Result<bool, StdError> {
+        let readback = self.readbacks.get(&handle.0).ok_or("Unknown readback handle! (GlRenderer)")?;
+
+        Ok(readback.poll()?)
+    }
+    /// Without `wait` it returns `None` while the GPU isn't done, the handle is consumed once data is returned.
+    pub(crate) fn take_readback(&mut self, handle: ReadbackHandle, wait: bool) -> Result<Option<Vec<u8>>, StdError> {
+        let readback = self.readbacks.get(&handle.0).ok_or("Unknown readback handle! (GlRenderer)")?;
+        if !wait && !readback.poll()? {
+            return Ok(None);
+        }
+        let data = readback.read(&mut self.state_cache)?;
+        self.discard_readback(handle);
+
+        Ok(Some(data))
+    }
+    /// Reads the default framebuffer's back buffer, so it has to happen before `end` swaps it. Rows are flipped to top first.
+    pub(crate) fn capture_frame(&mut self) -> Result<RgbaImage, StdError> {
+        let width = self.specs.gl_surface.width().ok_or("Failed to get surface width! (GlRenderer)")?;
+        let height = self.specs.gl_surface.height().ok_or("Failed to get surface height! (GlRenderer)")?;
+
+        let handle = self.read_pixels_async(0, 0, width, height)?;
+        let pixels = self.take_readback(handle, true)?.unwrap_or_default();
+        let mut image = RgbaImage::from_raw(width, height, pixels).ok_or("Captured frame has the wrong size! (GlRenderer)")?;
+        image::imageops::flip_vertical_in_place(&mut image);
+
+        Ok(image)
+    }
+    /// Rows are flipped to top first, the same as `capture_frame` for a texture that was drawn into.
+    pub(crate) fn capture_texture(&mut self, key: K, level: u32) -> Result<RgbaImage, StdError> {
+        let gl_tex = self.storage.textures.get(&key).ok_or("Failed to get Texture! (OpenGL)")?;
+        let (width, height) = gl_tex.rgba8_extent(level)?;
+
+        let handle = self.read_texture_async(key, level)?;
+        let pixels = self.take_readback(handle, true)?.unwrap_or_default();
+        let mut image = RgbaImage::from_raw(width, height, pixels).ok_or("Captured texture has the wrong size! (GlRenderer)")?;
+        image::imageops::flip_vertical_in_place(&mut image);
+
+        Ok(image)
+    }
+    pub(crate) fn discard_readback(&mut self, handle: ReadbackHandle) {
+        if let Some(readback) = self.readbacks.remove(&handle.0) {
+            self.state_cache.forget_buffer(readback.buffer().id());
+        }
     }
-    pub(crate) fn set_buffer_data(&self, key: K, data: &Vec<u8>) -> Result<(), StdError> {
-        gl_check!(gl::MemoryBarrier(gl::ALL_BARRIER_BITS), "Failed to wait for barriers!")?;
+    /// Replaces the whole buffer.
+    pub(crate) fn set_buffer_data<T: Pod>(&mut self, key: K, data: &[T]) -> Result<(), StdError> {
+        memory_barrier(gl::ALL_BARRIER_BITS)?;
         
-        if let Some(buffer) = self.storage.buffers.get(&key) {
-            let size = data.len() * std::mem::size_of::<u8>();
-            let data = data.as_ptr() as *const std::ffi::c_void;
-
-            buffer.bind()?;
-            buffer.set_data_full(
-                size, 
-                data,
-                gl::STATIC_DRAW
-            )?;
-            buffer.unbind()?;
-            
-            return Ok(());
+        let buffer = self.storage.buffers.get(&key).ok_or("Couldn't find buffer! (OpenGL)")?;
+        if !dsa() {
+            self.state_cache.bind_buffer(buffer.target(), buffer.id())?;
         }
+        buffer.set_data(data, gl::STATIC_DRAW)?;
+
+        Ok(())
+    }
+    /// `offset` counts elements of `T`, the buffer keeps its size.
+    pub(crate) fn write_buffer_slice<T: Pod>(&mut self, key: K, offset: usize, data: &[T]) -> Result<(), StdError> {
+        memory_barrier(gl::ALL_BARRIER_BITS)?;
         
-        Err("Couldn't find buffer! (OpenGL)".into())
+        let buffer = self.storage.buffers.get(&key).ok_or("Couldn't find buffer! (OpenGL)")?;
+        if !dsa() {
+            self.state_cache.bind_buffer(buffer.target(), buffer.id())?;
+        }
+        let Range { start: offset, end } = byte_range::<T>(offset, data.len())?;
+        let size = buffer.size()?;
+        if end > size {
+            return Err(format!("Writing bytes {}..{} of a {} byte buffer! (OpenGL)", offset, end, size).into());
+        }
+        buffer.sub_data(offset, data)?;
+
+        Ok(())
     }
     pub(crate) fn resize(&self, new_size: (u32, u32)) -> Result<(), StdError> {
         self.specs.gl_surface.resize(
@@ -284,28 +812,35 @@ impl<K: Eq + PartialEq + Hash + Default> GlRenderer<K> {
 }
 impl<K: Eq + PartialEq + Hash + Default + Clone> GraphicsApi for GlRenderer<K> {
     fn init(&mut self) -> Result<(), StdError> {
-        if true {
+        if self.specs.debug && (self.capabilities.version >= (4, 3) || self.capabilities.has_extension("GL_KHR_debug")) {
             gl_check!(gl::Enable(gl::DEBUG_OUTPUT), "Failed to enable gl::DEBUG_OUTPUT!")?;
             gl_check!(gl::DebugMessageCallback(Some(debug_callback), std::ptr::null()), "Failed to set DebugCallback")?;
         }
         
-        gl_check!(gl::Enable(gl::DEPTH_TEST), "Failed to enable DepthTest!")?;
+        self.state_cache.set_cap(gl::DEPTH_TEST, true)?;
         gl_check!(gl::DepthFunc(gl::LESS), "Failed to set DepthFunc!")?;
         gl_check!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA), "Failed to set BlendFunc!")?;
-        gl_check!(gl::Enable(gl::BLEND), "Failed to enable Blend!")?;
-        
-        gl_check!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32), "Failed to set texture parameter!")?;
-        gl_check!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32), "Failed to set texture parameter!")?;
-        gl_check!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32), "Failed to set texture parameter!")?;
-        gl_check!(gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32), "Failed to set texture parameter!")?;
+        self.state_cache.set_cap(gl::BLEND, true)?;
+        // Always on in GLES 3.
+        if self.specs.api == GlApi::OPEN_GL {
+            self.state_cache.set_cap(gl::TEXTURE_CUBE_MAP_SEAMLESS, true)?;
+        }
+        if self.specs.srgb {
+            self.state_cache.set_cap(gl::FRAMEBUFFER_SRGB, true)?;
+        }
 
         self.set_vsync(self.config.v_sync);
         Ok(())
     }
 
     fn shutdown(&mut self) -> Result<(), StdError> {
-        self.instance_vbo.unbind()?;
+        for vao in self.storage.vaos.values() {
+            vao.forget(&mut self.state_cache);
+        }
         self.storage.clear();
+        self.uniform_arena.clear(&mut self.state_cache);
+        self.readbacks.clear();
+        self.state_cache.invalidate();
 
         Ok(())
     }
@@ -325,6 +860,18 @@ impl<K: Eq + PartialEq + Hash> Drop for GlRenderer<K> {
     }
 }
 
+/// Bytes covered by `count` elements of `T` from element `first` on.
+fn byte_range<T>(first: usize, count: usize) -> Result<Range<usize>, StdError> {
+    let size = std::mem::size_of::<T>();
+    let start = first.checked_mul(size);
+    let len = count.checked_mul(size);
+
+    match start.zip(len).and_then(|(start, len)| Some(start..start.checked_add(len)?)) {
+        Some(bytes) => Ok(bytes),
+        None => Err(format!("{} elements from element {} overflow the byte range of a buffer! (OpenGL)", count, first).into()),
+    }
+}
+
 extern "system" fn debug_callback(
     source: gl::types::GLenum,
     gltype: gl::types::GLenum,
@@ -371,4 +918,18 @@ extern "system" fn debug_callback(
         "OpenGL Debug Message:\n  Source: {}\n  Type: {}\n  ID: {}\n  Severity: {}\n  Message: {}",
         source_str, gltype_str, id, severity_str, message_str
     );
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_ranges() {
+        assert_eq!(byte_range::<u32>(3, 2).unwrap(), 12..20);
+        assert_eq!(byte_range::<[f32; 4]>(0, 0).unwrap(), 0..0);
+        assert!(byte_range::<u64>(usize::MAX / 4, 1).is_err());
+        assert!(byte_range::<u32>(0, usize::MAX / 2).is_err());
+        assert!(byte_range::<u8>(usize::MAX, 1).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use crate::gl_check;
+
+use super::{gl_buffer::GlBuffer, gl_capabilities::dsa, gl_readback::wait_sync, gl_state::GlStateCache, GlError};
+
+/// Frames the GPU can lag behind before `next_frame` has to wait.
+pub(crate) const RING_FRAMES: usize = 3;
+/// Frames start at multiples of this, the largest offset alignment GL allows for uniform buffers.
+const FRAME_ALIGNMENT: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct GlRingAllocation {
+    /// In bytes from the start of the buffer.
+    pub(crate) offset: usize,
+    pub(crate) size: usize,
+}
+
+/// Streaming buffer split into one region per frame in flight, every region is fenced after its frame.
+/// With buffer storage the whole buffer stays mapped and allocations are plain copies,
+/// otherwise they fall back to `glBufferSubData`.
+pub(crate) struct GlRingBuffer {
+    buffer: GlBuffer,
+    frame_size: usize,
+    mapping: Option<*mut u8>,
+    fences: [gl::types::GLsync; RING_FRAMES],
+    frame: usize,
+    head: usize,
+}
+impl GlRingBuffer {
+    /// `frame_size` is rounded up so every frame starts aligned.
+    pub(crate) fn new(frame_size: usize, buffer_storage: bool) -> Result<Self, GlError> {
+        let (frame_size, size) = ring_size(frame_size)?;
+        let buffer = GlBuffer::new(gl::COPY_WRITE_BUFFER)?;
+        let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+
+        let mapping = if buffer_storage {
+            if dsa() {
+                gl_check!(gl::NamedBufferStorage(buffer.id(), size as gl::types::GLsizeiptr, std::ptr::null(), flags), "Failed to allocate ring buffer!")?;
+            } else {
+                gl_check!(gl::BindBuffer(gl::COPY_WRITE_BUFFER, buffer.id()), "Failed to bind buffer!")?;
+                gl_check!(gl::BufferStorage(gl::COPY_WRITE_BUFFER, size as gl::types::GLsizeiptr, std::ptr::null(), flags), "Failed to allocate ring buffer!")?;
+            }
+            Some(buffer.map_range(0, size, flags)? as *mut u8)
+        } else {
+            if !dsa() {
+                gl_check!(gl::BindBuffer(gl::COPY_WRITE_BUFFER, buffer.id()), "Failed to bind buffer!")?;
+            }
+            buffer.set_data_full(size, std::ptr::null(), gl::STREAM_DRAW)?;
+            None
+        };
+
+        Ok(Self {
+            buffer,
+            frame_size,
+            mapping,
+            fences: [std::ptr::null(); RING_FRAMES],
+            frame: 0,
+            head: 0,
+        })
+    }
+    pub(crate) fn id(&self) -> gl::types::GLuint {
+        self.buffer.id()
+    }
+    /// Copies `bytes` into the current frame's region, `align` is the offset alignment the binding needs.
+    pub(crate) fn allocate(&mut self, bytes: &[u8], align: usize, state_cache: &mut GlStateCache) -> Result<GlRingAllocation, GlError> {
+        let align = align.max(1);
+        let head = self.head.div_ceil(align) * align;
+        if head + bytes.len() > self.frame_size {
+            return Err(GlError::Error(
+                "Ring buffer full".to_string(),
+                format!(
+                    "{} bytes don't fit into the {} bytes left this frame, raise GlContextInfo::stream_buffer_size!",
+                    bytes.len(),
+                    self.frame_size.saturating_sub(head)
+                )
+            ));
+        }
+
+        let offset = self.frame * self.frame_size + head;
+        match self.mapping {
+            Some(mapping) => unsafe {
+                std::ptr::copy_nonoverlapping(bytes.as_ptr(), mapping.add(offset), bytes.len());
+            },
+            None => {
+                if !dsa() {
+                    state_cache.bind_buffer(gl::COPY_WRITE_BUFFER, self.buffer.id())?;
+                }
+                self.buffer.sub_data(offset, bytes)?;
+            },
+        }
+        self.head = head + bytes.len();
+
+        Ok(GlRingAllocation { offset, size: bytes.len() })
+    }
+    /// Fences the frame that was just submitted and waits until the GPU is done with the next region.
+    pub(crate) fn next_frame(&mut self) -> Result<(), GlError> {
+        gl_check!(self.fences[self.frame] = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0), "Failed to create fence!")?;
+        self.frame = (self.frame + 1) % RING_FRAMES;
+        self.head = 0;
+
+        let fence = std::mem::replace(&mut self.fences[self.frame], std::ptr::null());
+        if fence.is_null() {
+            return Ok(());
+        }
+        let mut signaled = Ok(false);
+        while let Ok(false) = signaled {
+            signaled = wait_sync(fence, 1_000_000_000);
+        }
+        gl_check!(gl::DeleteSync(fence), "Failed to delete fence!")?;
+
+        signaled.map(|_| ())
+    }
+}
+impl Drop for GlRingBuffer {
+    fn drop(&mut self) {
+        for fence in self.fences {
+            if !fence.is_null() {
+                gl_check!(gl::DeleteSync(fence), "Failed to delete fence!").unwrap();
+            }
+        }
+        if self.mapping.is_some() {
+            if !dsa() {
+                gl_check!(gl::BindBuffer(gl::COPY_WRITE_BUFFER, self.buffer.id()), "Failed to bind buffer!").unwrap();
+            }
+            self.buffer.unmap().unwrap();
+        }
+    }
+}
+
+/// (aligned frame size, buffer size) for `frame_size` bytes per frame.
+fn ring_size(frame_size: usize) -> Result<(usize, usize), GlError> {
+    frame_size
+        .checked_next_multiple_of(FRAME_ALIGNMENT)
+        .and_then(|frame_size| Some((frame_size, frame_size.checked_mul(RING_FRAMES)?)))
+        .filter(|(_, size)| *size <= isize::MAX as usize)
+        .ok_or_else(|| GlError::Error(
+            "Ring buffer too large".to_string(),
+            format!("{} frames of {} bytes don't fit in a buffer!", RING_FRAMES, frame_size)
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_are_aligned() {
+        assert_eq!(ring_size(0).unwrap(), (0, 0));
+        assert_eq!(ring_size(1000).unwrap(), (1024, 3072));
+        assert_eq!(ring_size(4096).unwrap(), (4096, 3 * 4096));
+        assert!(ring_size(usize::MAX).is_err());
+        assert!(ring_size(usize::MAX / 2).is_err());
+    }
+}
\ No newline at end of file
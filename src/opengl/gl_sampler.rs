@@ -0,0 +1,99 @@
+use crate::{gl_check, renderer_core::lg_sampler::{FilterMode, SamplerDescriptor, WrapMode}};
+
+use super::{gl_capabilities::{dsa, supported, GlFeature}, gl_pipeline::compare_op_to_opengl, GlError};
+
+// Core in 4.6 and not part of the generated bindings, same values as the EXT/ARB extensions.
+const TEXTURE_MAX_ANISOTROPY: gl::types::GLenum = 0x84FE;
+pub(crate) const MAX_TEXTURE_MAX_ANISOTROPY: gl::types::GLenum = 0x84FF;
+
+#[derive(Debug)]
+pub(crate) struct GlSampler {
+    id: gl::types::GLuint,
+}
+impl GlSampler {
+    pub(crate) fn new(descriptor: &SamplerDescriptor, max_anisotropy: f32) -> Result<Self, GlError> {
+        let mut id = 0;
+        if dsa() {
+            gl_check!(gl::CreateSamplers(1, &mut id), "Failed to create sampler!")?;
+        } else {
+            gl_check!(gl::GenSamplers(1, &mut id), "Failed to create sampler!")?;
+        }
+        
+        let sampler = Self { id };
+        sampler.set_parameters(descriptor, max_anisotropy)?;
+        
+        Ok(sampler)
+    }
+    pub(crate) fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+    fn set_parameters(&self, descriptor: &SamplerDescriptor, max_anisotropy: f32) -> Result<(), GlError> {
+        self.set_i(gl::TEXTURE_WRAP_S, wrap_to_opengl(descriptor.wrap.0))?;
+        self.set_i(gl::TEXTURE_WRAP_T, wrap_to_opengl(descriptor.wrap.1))?;
+        self.set_i(gl::TEXTURE_WRAP_R, wrap_to_opengl(descriptor.wrap.2))?;
+        self.set_i(gl::TEXTURE_MIN_FILTER, min_filter_to_opengl(descriptor.min_filter, descriptor.mip_filter))?;
+        self.set_i(gl::TEXTURE_MAG_FILTER, filter_to_opengl(descriptor.mag_filter))?;
+        
+        if supported(GlFeature::LOD_BIAS) {
+            self.set_f(gl::TEXTURE_LOD_BIAS, descriptor.lod_bias)?;
+        }
+        self.set_f(gl::TEXTURE_MIN_LOD, descriptor.min_lod)?;
+        self.set_f(gl::TEXTURE_MAX_LOD, descriptor.max_lod)?;
+        if supported(GlFeature::BORDER_CLAMP) {
+            gl_check!(
+                gl::SamplerParameterfv(self.id, gl::TEXTURE_BORDER_COLOR, descriptor.border_color.as_ptr()),
+                "Failed to set sampler border color!"
+            )?;
+        }
+        
+        // 0 means the driver has no anisotropic filtering.
+        if max_anisotropy > 0.0 {
+            self.set_f(TEXTURE_MAX_ANISOTROPY, descriptor.anisotropy.clamp(1.0, max_anisotropy))?;
+        }
+        
+        match descriptor.compare {
+            Some(op) => {
+                self.set_i(gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE)?;
+                self.set_i(gl::TEXTURE_COMPARE_FUNC, compare_op_to_opengl(op))
+            },
+            None => self.set_i(gl::TEXTURE_COMPARE_MODE, gl::NONE),
+        }
+    }
+    fn set_i(&self, parameter: gl::types::GLenum, value: gl::types::GLenum) -> Result<(), GlError> {
+        gl_check!(gl::SamplerParameteri(self.id, parameter, value as i32), "Failed to set sampler parameter!")
+    }
+    fn set_f(&self, parameter: gl::types::GLenum, value: f32) -> Result<(), GlError> {
+        gl_check!(gl::SamplerParameterf(self.id, parameter, value), "Failed to set sampler parameter!")
+    }
+}
+impl Drop for GlSampler {
+    fn drop(&mut self) {
+        gl_check!(gl::DeleteSamplers(1, [self.id].as_ptr()), "Failed to delete sampler!").unwrap();
+    }
+}
+
+fn wrap_to_opengl(wrap: WrapMode) -> gl::types::GLenum {
+    match wrap {
+        WrapMode::REPEAT => gl::REPEAT,
+        WrapMode::MIRRORED_REPEAT => gl::MIRRORED_REPEAT,
+        WrapMode::CLAMP_TO_EDGE => gl::CLAMP_TO_EDGE,
+        // Without border clamping the closest thing is the edge texel.
+        WrapMode::CLAMP_TO_BORDER if !supported(GlFeature::BORDER_CLAMP) => gl::CLAMP_TO_EDGE,
+        WrapMode::CLAMP_TO_BORDER => gl::CLAMP_TO_BORDER,
+    }
+}
+fn filter_to_opengl(filter: FilterMode) -> gl::types::GLenum {
+    match filter {
+        FilterMode::NEAREST => gl::NEAREST,
+        FilterMode::LINEAR => gl::LINEAR,
+    }
+}
+fn min_filter_to_opengl(min: FilterMode, mip: Option<FilterMode>) -> gl::types::GLenum {
+    match (min, mip) {
+        (min, None) => filter_to_opengl(min),
+        (FilterMode::NEAREST, Some(FilterMode::NEAREST)) => gl::NEAREST_MIPMAP_NEAREST,
+        (FilterMode::NEAREST, Some(FilterMode::LINEAR)) => gl::NEAREST_MIPMAP_LINEAR,
+        (FilterMode::LINEAR, Some(FilterMode::NEAREST)) => gl::LINEAR_MIPMAP_NEAREST,
+        (FilterMode::LINEAR, Some(FilterMode::LINEAR)) => gl::LINEAR_MIPMAP_LINEAR,
+    }
+}
\ No newline at end of file
@@ -0,0 +1,259 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::gl_check;
+
+use super::{gl_capabilities::{dsa, supported, GlFeature}, GlError};
+
+/// Nothing to wait for where shaders can't write memory (without image load/store), so it's skipped there.
+pub(crate) fn memory_barrier(barriers: gl::types::GLbitfield) -> Result<(), GlError> {
+    if !supported(GlFeature::IMAGE_LOAD_STORE) {
+        return Ok(());
+    }
+
+    gl_check!(gl::MemoryBarrier(barriers), "Failed to issue memory barrier!")
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StateCounter {
+    pub issued: u64,
+    pub skipped: u64,
+}
+impl StateCounter {
+    fn record(&mut self, issued: bool) {
+        if issued {
+            self.issued += 1;
+        } else {
+            self.skipped += 1;
+        }
+    }
+}
+
+/// How many state changes reached the driver and how many were skipped because nothing changed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GlStateCounters {
+    pub programs: StateCounter,
+    pub vertex_arrays: StateCounter,
+    pub buffers: StateCounter,
+    pub textures: StateCounter,
+    pub samplers: StateCounter,
+    pub caps: StateCounter,
+}
+impl GlStateCounters {
+    pub fn total(&self) -> StateCounter {
+        let all = [self.programs, self.vertex_arrays, self.buffers, self.textures, self.samplers, self.caps];
+
+        StateCounter {
+            issued: all.iter().map(|c| c.issued).sum(),
+            skipped: all.iter().map(|c| c.skipped).sum(),
+        }
+    }
+}
+
+/// (buffer, (offset, size) of the bound range).
+type BufferBinding = (gl::types::GLuint, Option<(usize, usize)>);
+
+/// Shadow copy of the GL bindings, `None` or a missing entry means unknown.
+#[derive(Debug, Default)]
+pub(crate) struct GlStateCache {
+    program: Option<gl::types::GLuint>,
+    vao: Option<gl::types::GLuint>,
+    buffers: HashMap<gl::types::GLenum, gl::types::GLuint>,
+    /// (target, index) -> (buffer, range), `None` for the whole buffer.
+    buffer_bases: HashMap<(gl::types::GLenum, gl::types::GLuint), BufferBinding>,
+    active_texture: Option<u32>,
+    textures: HashMap<(u32, gl::types::GLenum), gl::types::GLuint>,
+    samplers: HashMap<u32, gl::types::GLuint>,
+    caps: HashMap<gl::types::GLenum, bool>,
+    /// Textures bound for image stores since the last barrier.
+    written_images: HashSet<gl::types::GLuint>,
+
+    counters: GlStateCounters,
+}
+impl GlStateCache {
+    pub(crate) fn counters(&self) -> &GlStateCounters {
+        &self.counters
+    }
+    pub(crate) fn reset_counters(&mut self) {
+        self.counters = GlStateCounters::default();
+    }
+    /// Forgets every cached binding, used when GL state was changed behind the cache's back.
+    /// Pending image writes are kept, those still need their barrier.
+    pub(crate) fn invalidate(&mut self) {
+        let counters = self.counters;
+        let written_images = std::mem::take(&mut self.written_images);
+        *self = Self { counters, written_images, ..Default::default() };
+    }
+    pub(crate) fn invalidate_texture_unit(&mut self, unit: u32) {
+        self.active_texture = None;
+        self.textures.retain(|(u, _), _| *u != unit);
+    }
+    /// GL unbinds deleted textures from every unit and can hand out their names again.
+    pub(crate) fn forget_texture(&mut self, id: gl::types::GLuint) {
+        self.textures.retain(|_, bound| *bound != id);
+        self.written_images.remove(&id);
+    }
+    /// A deleted vertex array that was bound reverts the binding to 0.
+    pub(crate) fn forget_vertex_array(&mut self, id: gl::types::GLuint) {
+        if self.vao == Some(id) {
+            self.vao = Some(0);
+            self.buffers.remove(&gl::ELEMENT_ARRAY_BUFFER);
+        }
+    }
+    /// GL unbinds deleted buffers and can hand out their names again.
+    pub(crate) fn forget_buffer(&mut self, id: gl::types::GLuint) {
+        self.buffers.retain(|_, bound| *bound != id);
+        self.buffer_bases.retain(|_, (bound, _)| *bound != id);
+    }
+
+    pub(crate) fn use_program(&mut self, id: gl::types::GLuint) -> Result<(), GlError> {
+        let issue = self.program != Some(id);
+        self.counters.programs.record(issue);
+
+        if issue {
+            gl_check!(gl::UseProgram(id), "Failed to use shader program!")?;
+            self.program = Some(id);
+        }
+
+        Ok(())
+    }
+    pub(crate) fn bind_vertex_array(&mut self, id: gl::types::GLuint) -> Result<(), GlError> {
+        let issue = self.vao != Some(id);
+        self.counters.vertex_arrays.record(issue);
+
+        if issue {
+            gl_check!(gl::BindVertexArray(id), "Failed to bind vertex array!")?;
+            self.vao = Some(id);
+            // The element array binding is part of the VAO state.
+            self.buffers.remove(&gl::ELEMENT_ARRAY_BUFFER);
+        }
+
+        Ok(())
+    }
+    pub(crate) fn bind_buffer(&mut self, target: gl::types::GLenum, id: gl::types::GLuint) -> Result<(), GlError> {
+        let issue = self.buffers.get(&target) != Some(&id);
+        self.counters.buffers.record(issue);
+
+        if issue {
+            gl_check!(gl::BindBuffer(target, id), "Failed to bind buffer!")?;
+            self.buffers.insert(target, id);
+        }
+
+        Ok(())
+    }
+    pub(crate) fn bind_buffer_base(
+        &mut self,
+        target: gl::types::GLenum,
+        index: gl::types::GLuint,
+        id: gl::types::GLuint
+    ) -> Result<(), GlError>
+    {
+        let issue = self.buffer_bases.get(&(target, index)) != Some(&(id, None));
+        self.counters.buffers.record(issue);
+
+        if issue {
+            gl_check!(gl::BindBufferBase(target, index, id), "Failed to bind base!")?;
+            self.buffer_bases.insert((target, index), (id, None));
+            // glBindBufferBase also binds to the generic binding point.
+            self.buffers.insert(target, id);
+        }
+
+        Ok(())
+    }
+    /// `offset` and `size` are in bytes, `offset` has to respect the target's offset alignment.
+    pub(crate) fn bind_buffer_range(
+        &mut self,
+        target: gl::types::GLenum,
+        index: gl::types::GLuint,
+        id: gl::types::GLuint,
+        offset: usize,
+        size: usize
+    ) -> Result<(), GlError>
+    {
+        let range = Some((offset, size));
+        let issue = self.buffer_bases.get(&(target, index)) != Some(&(id, range));
+        self.counters.buffers.record(issue);
+
+        if issue {
+            gl_check!(
+                gl::BindBufferRange(target, index, id, offset as gl::types::GLintptr, size as gl::types::GLsizeiptr),
+                "Failed to bind range!"
+            )?;
+            self.buffer_bases.insert((target, index), (id, range));
+            // Like glBindBufferBase this also binds to the generic binding point.
+            self.buffers.insert(target, id);
+        }
+
+        Ok(())
+    }
+    pub(crate) fn bind_texture(
+        &mut self,
+        unit: u32,
+        target: gl::types::GLenum,
+        id: gl::types::GLuint
+    ) -> Result<(), GlError>
+    {
+        let issue = self.textures.get(&(unit, target)) != Some(&id);
+        self.counters.textures.record(issue);
+
+        if issue {
+            if dsa() {
+                gl_check!(gl::BindTextureUnit(unit, id), "Failed to bind texture! (binding)")?;
+            } else {
+                if self.active_texture != Some(unit) {
+                    gl_check!(gl::ActiveTexture(gl::TEXTURE0 + unit), "Failed to activate texture! (binding)")?;
+                    self.active_texture = Some(unit);
+                }
+                gl_check!(gl::BindTexture(target, id), "Failed to bind texture! (binding)")?;
+            }
+            self.textures.insert((unit, target), id);
+        }
+
+        Ok(())
+    }
+    pub(crate) fn bind_sampler(&mut self, unit: u32, id: gl::types::GLuint) -> Result<(), GlError> {
+        let issue = self.samplers.get(&unit) != Some(&id);
+        self.counters.samplers.record(issue);
+
+        if issue {
+            gl_check!(gl::BindSampler(unit, id), "Failed to bind sampler!")?;
+            self.samplers.insert(unit, id);
+        }
+
+        Ok(())
+    }
+    pub(crate) fn image_written(&mut self, id: gl::types::GLuint) {
+        self.written_images.insert(id);
+    }
+    /// Called before a texture is sampled, bound as an image or updated. If an image store may have
+    /// written it, one barrier covering every such access is issued, which also covers all other pending writes.
+    pub(crate) fn image_barrier(&mut self, id: gl::types::GLuint) -> Result<(), GlError> {
+        if !self.written_images.contains(&id) {
+            return Ok(());
+        }
+
+        memory_barrier(
+            gl::TEXTURE_FETCH_BARRIER_BIT
+                | gl::SHADER_IMAGE_ACCESS_BARRIER_BIT
+                | gl::TEXTURE_UPDATE_BARRIER_BIT
+                | gl::FRAMEBUFFER_BARRIER_BIT
+        )?;
+        self.written_images.clear();
+
+        Ok(())
+    }
+    pub(crate) fn set_cap(&mut self, cap: gl::types::GLenum, enabled: bool) -> Result<(), GlError> {
+        let issue = self.caps.get(&cap) != Some(&enabled);
+        self.counters.caps.record(issue);
+
+        if issue {
+            if enabled {
+                gl_check!(gl::Enable(cap), "Failed to enable capability!")?;
+            } else {
+                gl_check!(gl::Disable(cap), "Failed to disable capability!")?;
+            }
+            self.caps.insert(cap, enabled);
+        }
+
+        Ok(())
+    }
+}
\ No newline at end of file
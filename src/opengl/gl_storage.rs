@@ -1,15 +1,17 @@
-use std::{collections::HashMap, hash::Hash};
-use crate::renderer_core::{lg_shader::LgShader, lg_texture::LgTexture, lg_uniform::LgUniform};
-use super::{gl_buffer::GlBuffer, gl_program::GlProgram, gl_shader::GlShader, gl_texture::GlTexture, gl_vertex_array::GlVertexArray};
+use std::{collections::{hash_map::Entry, HashMap}, hash::Hash};
+use crate::renderer_core::{lg_pipeline::PipelineDescriptor, lg_sampler::SamplerDescriptor, lg_shader::LgShader, lg_texture::LgTexture, lg_uniform::LgUniform};
+use super::{gl_buffer::GlBuffer, gl_capabilities::dsa, gl_pipeline::GlPipeline, gl_program::GlProgram, gl_sampler::GlSampler, gl_shader::GlShader, gl_texture::{validate_texture, GlTexture}, gl_vertex_array::GlVertexArray, GlError};
 
 #[derive(Default)]
 pub(crate) struct GlStorage<K: Eq + PartialEq + Hash> {
     pub(crate) buffers: HashMap<K, GlBuffer>,
     pub(crate) textures: HashMap<K, GlTexture>,
+    samplers: HashMap<SamplerDescriptor, GlSampler>,
     shaders: HashMap<K, GlShader>,
 
     pub(crate) vaos: HashMap<K, GlVertexArray>,
     pub(crate) programs: HashMap<K, GlProgram>,
+    pub(crate) pipelines: HashMap<K, GlPipeline<K>>,
 }
 impl<K: Clone + Eq + PartialEq + Hash> GlStorage<K> {
     pub(crate) fn set_vao(&mut self, key: K) -> (bool, &mut GlVertexArray) {
@@ -32,45 +34,69 @@ impl<K: Clone + Eq + PartialEq + Hash> GlStorage<K> {
             program
         })
     }
-    pub(crate) fn set_texture<T: LgTexture>(&mut self, key: K, texture: &T, location: u32) {
-        self.textures.entry(key).or_insert_with(|| {
-            let gl_tex = GlTexture::new().unwrap();
-            gl_tex.bind(location).unwrap();
-            gl_tex.load(texture).unwrap();
-            
-            gl_tex
-        });
+    /// Unlike programs, a pipeline with the same key is replaced.
+    pub(crate) fn set_pipeline<S: LgShader>(&mut self, key: K, descriptor: PipelineDescriptor<K, S>) {
+        let (program_key, shaders) = descriptor.shaders;
+        self.set_program(program_key.clone(), shaders);
+
+        self.pipelines.insert(key, GlPipeline::new(
+            program_key,
+            descriptor.vertex_layout,
+            descriptor.topology,
+            descriptor.state,
+        ));
     }
-    pub(crate) fn set_uniforms(&mut self, uniforms: &[(K, &impl LgUniform)]) {
-        for (key, ubo) in uniforms {
-            self.buffers.entry(key.clone()).or_insert_with(|| {
-                let usage = match ubo.u_type() {
-                    crate::renderer_core::lg_uniform::LgUniformType::STRUCT => gl::UNIFORM_BUFFER,
-                    crate::renderer_core::lg_uniform::LgUniformType::STORAGE_BUFFER => gl::SHADER_STORAGE_BUFFER,
-                    crate::renderer_core::lg_uniform::LgUniformType::COMBINED_IMAGE_SAMPLER => gl::SAMPLER_2D,
-                };
-                
-                let buffer = GlBuffer::new(usage).unwrap();
-                
-                buffer.bind().unwrap();
-                buffer.bind_base(ubo.binding()).unwrap();
-                buffer.set_data_full(
-                    ubo.data_size(), 
-                    ubo.get_raw_data(),
-                    gl::STATIC_DRAW,
-                ).unwrap();
-                buffer.unbind().unwrap();
-                
-                buffer
-            });
+    /// Reallocates the texture when `texture` no longer fits the existing storage.
+    /// The texture is checked before any GL object is created, an invalid one leaves nothing behind.
+    pub(crate) fn set_texture<T: LgTexture>(&mut self, key: K, texture: &T, location: u32) -> Result<(bool, &GlTexture), GlError> {
+        if self.textures.get(&key).is_some_and(|gl_tex| !gl_tex.matches(texture)) {
+            self.textures.remove(&key);
         }
+
+        match self.textures.entry(key) {
+            Entry::Occupied(entry) => Ok((true, entry.into_mut())),
+            Entry::Vacant(entry) => {
+                validate_texture(texture)?;
+                let mut gl_tex = GlTexture::new(texture.dimension())?;
+                if !dsa() {
+                    gl_tex.bind(location)?;
+                }
+                gl_tex.load(texture)?;
+
+                Ok((false, entry.insert(gl_tex)))
+            },
+        }
+    }
+    pub(crate) fn set_sampler(&mut self, descriptor: &SamplerDescriptor, max_anisotropy: f32) -> Result<&GlSampler, GlError> {
+        match self.samplers.entry(*descriptor) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => Ok(entry.insert(GlSampler::new(descriptor, max_anisotropy)?)),
+        }
+    }
+    pub(crate) fn set_uniform(&mut self, key: K, ubo: &impl LgUniform) -> (bool, &GlBuffer) {
+        let mut present = true;
+        let buffer = self.buffers.entry(key).or_insert_with(|| {
+            present = false;
+            let usage = match ubo.u_type() {
+                crate::renderer_core::lg_uniform::LgUniformType::STRUCT => gl::UNIFORM_BUFFER,
+                crate::renderer_core::lg_uniform::LgUniformType::STORAGE_BUFFER => gl::SHADER_STORAGE_BUFFER,
+                crate::renderer_core::lg_uniform::LgUniformType::STORAGE_IMAGE => unreachable!("Images are bound through set_images!"),
+                crate::renderer_core::lg_uniform::LgUniformType::COMBINED_IMAGE_SAMPLER => unreachable!("Samplers are bound through set_textures!"),
+            };
+            
+            GlBuffer::new(usage).unwrap()
+        });
+        
+        (present, buffer)
     }
     pub(crate) fn clear(&mut self) {
         self.buffers.clear();
         self.shaders.clear();
         self.textures.clear();
+        self.samplers.clear();
         self.vaos.clear();
         self.programs.clear();
+        self.pipelines.clear();
     }
     
     fn set_shaders<S: LgShader>(&mut self, shaders: &[(K, &S)]) -> Vec<gl::types::GLuint> {
@@ -84,4 +110,29 @@ impl<K: Clone + Eq + PartialEq + Hash> GlStorage<K> {
         
         result
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer_core::lg_texture::{TextureFormat, TextureType};
+
+    use super::*;
+
+    struct Short;
+    impl LgTexture for Short {
+        fn width(&self) -> u32 { 4 }
+        fn height(&self) -> u32 { 4 }
+        fn bytes(&self) -> &[u8] { &[0; 63] }
+        fn size(&self) -> u64 { 63 }
+        fn mip_level(&self) -> u32 { 1 }
+        fn texture_type(&self) -> TextureType { TextureType::UNSIGNED_BYTE }
+        fn texture_format(&self) -> TextureFormat { TextureFormat::RGBA }
+    }
+
+    #[test]
+    fn invalid_textures_are_errors() {
+        let mut storage = GlStorage::<u32>::default();
+        assert!(storage.set_texture(0, &Short, 0).is_err());
+        assert!(storage.textures.is_empty());
+    }
 }
\ No newline at end of file
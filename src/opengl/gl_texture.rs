@@ -1,43 +1,569 @@
-use crate::{gl_check, renderer_core::lg_texture::{LgTexture, TextureFormat, TextureType}};
+use std::collections::HashMap;
 
-use super::GlError;
+use crate::{gl_check, renderer_core::lg_texture::{bytes_per_pixel, full_mip_count, image_size, mip_extent, CompressedFormat, LgTexture, MipMode, TextureDimension, TextureFormat, TextureRegion, TextureType}};
 
-#[derive(Debug, Default)]
+use super::{gl_bindless::{make_non_resident, make_resident, texture_sampler_handle}, gl_buffer::GlBuffer, gl_capabilities::{dsa, supported, GlFeature}, GlError};
+
+/// What the texture was allocated with, needed for sub-updates and to detect reallocations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GlTextureInfo {
+    dimension: TextureDimension,
+    width: u32,
+    height: u32,
+    depth: u32,
+    format: TextureFormat,
+    ty: TextureType,
+    compression: Option<CompressedFormat>,
+    levels: u32,
+    /// Whether levels past the base are generated after every upload.
+    generate_mips: bool,
+}
+
+#[derive(Debug)]
 pub(crate) struct GlTexture {
     id: gl::types::GLuint,
+    target: gl::types::GLenum,
+    info: Option<GlTextureInfo>,
+    /// Pixel unpack buffer for streaming uploads, created on first use.
+    stream_buffer: Option<GlBuffer>,
+    /// Resident bindless handles by sampler, deleting the texture frees them.
+    handles: HashMap<gl::types::GLuint, gl::types::GLuint64>,
 }
 impl GlTexture {
-    pub(crate) fn new() -> Result<Self, GlError> {
+    pub(crate) fn new(dimension: TextureDimension) -> Result<Self, GlError> {
+        let target = dimension_to_opengl(dimension);
         let mut id = 0;
-        gl_check!(gl::GenTextures(1, &mut id), "Failed to generate texture!")?;
+        if dsa() {
+            gl_check!(gl::CreateTextures(target, 1, &mut id), "Failed to generate texture!")?;
+        } else {
+            gl_check!(gl::GenTextures(1, &mut id), "Failed to generate texture!")?;
+        }
         
-        Ok(Self { id })
+        Ok(Self { 
+            id, 
+            target,
+            info: None,
+            stream_buffer: None,
+            handles: HashMap::new(),
+        })
+    }
+    pub(crate) fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+    pub(crate) fn target(&self) -> gl::types::GLenum {
+        self.target
     }
     pub(crate) fn bind(&self, location: u32) -> Result<(), GlError> {
         gl_check!(gl::ActiveTexture(gl::TEXTURE0 + location), "Failed to activate texture! (binding)")?;
-        gl_check!(gl::BindTexture(gl::TEXTURE_2D, self.id), "Failed to bind texture! (binding)")
+        gl_check!(gl::BindTexture(self.target, self.id), "Failed to bind texture! (binding)")
+    }
+    /// Whether `texture` fits the storage this texture was allocated with, mip levels included.
+    pub(crate) fn matches(&self, texture: &impl LgTexture) -> bool {
+        self.info.is_some_and(|info| {
+            info.dimension == texture.dimension()
+                && info.width == texture.width()
+                && info.height == texture.height()
+                && info.depth == texture.depth()
+                && info.format == texture.texture_format()
+                && info.ty == texture.texture_type()
+                && info.compression == texture.compression()
+                && (info.levels, info.generate_mips) == mip_levels(texture)
+        })
+    }
+    /// Resident handles are invalidated when the texture is deleted.
+    pub(crate) fn has_handles(&self) -> bool {
+        !self.handles.is_empty()
     }
-    pub(crate) fn unbind(&self) -> Result<(), GlError> {
-        gl_check!(gl::BindTexture(gl::TEXTURE_2D, 0), "Failed to unbind texture!")
+    /// Without DSA the texture has to be bound beforehand.
+    pub(crate) fn load(&mut self, texture: &impl LgTexture) -> Result<(), GlError> {
+        let format = validate_texture(texture)?;
+        let dimension = texture.dimension();
+        let (width, height, depth) = (texture.width(), texture.height(), texture.depth());
+        let (levels, generate_mips) = mip_levels(texture);
+        let provided_levels = if texture.mip_mode() == MipMode::PROVIDED { levels } else { 1 };
+
+        self.info = Some(GlTextureInfo {
+            dimension,
+            width,
+            height,
+            depth,
+            format: texture.texture_format(),
+            ty: texture.texture_type(),
+            compression: texture.compression(),
+            levels,
+            generate_mips,
+        });
+        self.allocate(&format)?;
+
+        let mut offset = 0;
+        for level in 0..provided_levels {
+            let (level_width, level_height, level_depth) = mip_extent(width, height, depth, dimension, level);
+            let region = TextureRegion {
+                width: level_width,
+                height: level_height,
+                depth: level_depth,
+                ..Default::default()
+            };
+            self.sub_image(level, &region, texture.bytes()[offset..].as_ptr() as *const _)?;
+            offset += region_size(&region, &self.info.unwrap());
+        }
+        
+        if generate_mips {
+            self.generate_mipmaps()?;
+        }
+
+        Ok(())
+    }
+    /// Resident bindless handle for sampling this texture with `sampler`.
+    pub(crate) fn bindless_handle(&mut self, sampler: gl::types::GLuint) -> Result<gl::types::GLuint64, GlError> {
+        if let Some(handle) = self.handles.get(&sampler) {
+            return Ok(*handle);
+        }
+
+        let handle = texture_sampler_handle(self.id, sampler)?;
+        make_resident(handle)?;
+        self.handles.insert(sampler, handle);
+
+        Ok(handle)
     }
-    pub(crate) fn load(&self, texture: &impl LgTexture) -> Result<(), GlError> {
+    pub(crate) fn release_handles(&mut self) -> Result<(), GlError> {
+        for (_, handle) in self.handles.drain() {
+            make_non_resident(handle)?;
+        }
+
+        Ok(())
+    }
+    /// Rebuilds every level past the base one, e.g. after rendering into the texture.
+    /// Without DSA the texture has to be bound beforehand.
+    pub(crate) fn generate_mipmaps(&self) -> Result<(), GlError> {
+        if self.info.is_none_or(|info| info.levels < 2) {
+            return Ok(());
+        }
+
+        if dsa() {
+            gl_check!(gl::GenerateTextureMipmap(self.id), "Failed to generate mip map for texture!")
+        } else {
+            gl_check!(gl::GenerateMipmap(self.target), "Failed to generate mip map!")
+        }
+    }
+    fn allocate(&self, format: &GlFormat) -> Result<(), GlError> {
+        let info = self.info.unwrap();
+        let (width, height, depth) = (info.width as i32, info.height as i32, info.depth as i32);
+        let levels = info.levels as i32;
+        let layered = !matches!(info.dimension, TextureDimension::TEXTURE_2D | TextureDimension::CUBE);
+        
+        if dsa() {
+            return if layered {
+                gl_check!(
+                    gl::TextureStorage3D(self.id, levels, format.internal, width, height, depth),
+                    "Failed to allocate texture storage!"
+                )
+            } else {
+                gl_check!(
+                    gl::TextureStorage2D(self.id, levels, format.internal, width, height),
+                    "Failed to allocate texture storage!"
+                )
+            };
+        }
+        // Immutable storage is core since 4.2 and GLES 3.0.
+        if supported(GlFeature::TEXTURE_STORAGE) {
+            return if layered {
+                gl_check!(
+                    gl::TexStorage3D(self.target, levels, format.internal, width, height, depth),
+                    "Failed to allocate texture storage!"
+                )
+            } else {
+                gl_check!(
+                    gl::TexStorage2D(self.target, levels, format.internal, width, height),
+                    "Failed to allocate texture storage!"
+                )
+            };
+        }
+
+        // Mutable storage is only complete once every level up to TEXTURE_MAX_LEVEL exists.
+        for level in 0..info.levels {
+            let (level_width, level_height, level_depth) = mip_extent(info.width, info.height, info.depth, info.dimension, level);
+            let level_size = image_size(level_width, level_height, level_depth, info.format, info.ty, info.compression);
+            let (level_width, level_height, level_depth) = (level_width as i32, level_height as i32, level_depth as i32);
+
+            match (info.dimension, info.compression.is_some()) {
+                (TextureDimension::TEXTURE_2D, false) => gl_check!(
+                    gl::TexImage2D(
+                        self.target, 
+                        level as i32, 
+                        format.internal as i32,
+                        level_width, 
+                        level_height, 
+                        0, 
+                        format.format, 
+                        format.ty, 
+                        std::ptr::null(),
+                    ),
+                    "Failed to allocate texture!"
+                )?,
+                (TextureDimension::TEXTURE_2D, true) => gl_check!(
+                    gl::CompressedTexImage2D(
+                        self.target, 
+                        level as i32, 
+                        format.internal,
+                        level_width, 
+                        level_height, 
+                        0, 
+                        level_size as i32,
+                        std::ptr::null(),
+                    ),
+                    "Failed to allocate compressed texture!"
+                )?,
+                (TextureDimension::CUBE, compressed) => for face in 0..6 {
+                    let face_target = gl::TEXTURE_CUBE_MAP_POSITIVE_X + face;
+                    if compressed {
+                        gl_check!(
+                            gl::CompressedTexImage2D(
+                                face_target, 
+                                level as i32, 
+                                format.internal,
+                                level_width, 
+                                level_height, 
+                                0, 
+                                (level_size / 6) as i32,
+                                std::ptr::null(),
+                            ),
+                            "Failed to allocate compressed cube map face!"
+                        )?;
+                    } else {
+                        gl_check!(
+                            gl::TexImage2D(
+                                face_target, 
+                                level as i32, 
+                                format.internal as i32,
+                                level_width, 
+                                level_height, 
+                                0, 
+                                format.format, 
+                                format.ty, 
+                                std::ptr::null(),
+                            ),
+                            "Failed to allocate cube map face!"
+                        )?;
+                    }
+                },
+                (_, false) => gl_check!(
+                    gl::TexImage3D(
+                        self.target, 
+                        level as i32, 
+                        format.internal as i32,
+                        level_width, 
+                        level_height, 
+                        level_depth,
+                        0, 
+                        format.format, 
+                        format.ty, 
+                        std::ptr::null(),
+                    ),
+                    "Failed to allocate texture!"
+                )?,
+                (_, true) => gl_check!(
+                    gl::CompressedTexImage3D(
+                        self.target, 
+                        level as i32, 
+                        format.internal,
+                        level_width, 
+                        level_height, 
+                        level_depth,
+                        0, 
+                        level_size as i32,
+                        std::ptr::null(),
+                    ),
+                    "Failed to allocate compressed texture!"
+                )?,
+            }
+        }
         gl_check!(
-            gl::TexImage2D(
-                gl::TEXTURE_2D, 
-                0, 
-                tex_format_to_opengl(texture.texture_format()) as i32,
-                texture.width() as i32, 
-                texture.height() as i32, 
-                0, 
-                tex_format_to_opengl(texture.texture_format()), 
-                tex_type_to_opengl(texture.texture_type()), 
-                texture.bytes().as_ptr() as *const _,
+            gl::TexParameteri(self.target, gl::TEXTURE_MAX_LEVEL, levels - 1),
+            "Failed to set texture max level!"
+        )
+    }
+}
+impl GlTexture {
+    /// Without DSA the texture has to be bound beforehand.
+    pub(crate) fn update(&self, region: &TextureRegion, bytes: &[u8]) -> Result<(), GlError> {
+        let expected = self.validate_region(region)?;
+        if bytes.len() < expected {
+            return Err(GlError::Error(
+                "Texture size mismatch".to_string(),
+                format!("Region {:?} needs {} bytes, got {}!", region, expected, bytes.len())
+            ));
+        }
+
+        self.sub_image(0, region, bytes.as_ptr() as *const _)?;
+        self.regenerate_if_needed()
+    }
+    /// Size in bytes of a whole mip level, all layers and faces included.
+    pub(crate) fn level_size(&self, level: u32) -> Result<usize, GlError> {
+        let info = self.info.ok_or_else(|| GlError::Error("Texture not loaded".to_string(), "The texture has no storage yet!".to_string()))?;
+        if level >= info.levels {
+            return Err(GlError::Error(
+                "Invalid mip level".to_string(),
+                format!("Level {} of a texture with {} levels!", level, info.levels)
+            ));
+        }
+        let (width, height, depth) = mip_extent(info.width, info.height, info.depth, info.dimension, level);
+
+        Ok(image_size(width, height, depth, info.format, info.ty, info.compression))
+    }
+    /// (width, height) of `level`, only 2D RGBA8 textures can be read back as an image.
+    pub(crate) fn rgba8_extent(&self, level: u32) -> Result<(u32, u32), GlError> {
+        self.level_size(level)?;
+        let info = self.info.unwrap();
+        let rgba8 = matches!(info.format, TextureFormat::RGBA | TextureFormat::SRGB_ALPHA)
+            && info.ty == TextureType::UNSIGNED_BYTE
+            && info.compression.is_none()
+            && info.dimension == TextureDimension::TEXTURE_2D;
+        if !rgba8 {
+            return Err(GlError::Error(
+                "Texture not RGBA8".to_string(),
+                format!("Only 2D RGBA8 textures can be captured, got {:?} {:?} {:?}!", info.dimension, info.format, info.ty)
+            ));
+        }
+        let (width, height, _) = mip_extent(info.width, info.height, info.depth, info.dimension, level);
+
+        Ok((width, height))
+    }
+    /// Packs a whole mip level into the buffer bound to `GL_PIXEL_PACK_BUFFER`, tightly packed.
+    /// Without DSA the texture has to be bound beforehand, GLES can't read textures back this way.
+    pub(crate) fn pack_level(&self, level: u32) -> Result<(), GlError> {
+        let size = self.level_size(level)?;
+        let info = self.info.unwrap();
+        let format = GlFormat::new(info.format, info.ty, info.compression)?;
+        gl_check!(gl::PixelStorei(gl::PACK_ALIGNMENT, 1), "Failed to set pack alignment!")?;
+
+        if dsa() {
+            return match info.compression {
+                Some(_) => gl_check!(
+                    gl::GetCompressedTextureImage(self.id, level as i32, size as i32, std::ptr::null_mut()),
+                    "Failed to read compressed texture!"
+                ),
+                None => gl_check!(
+                    gl::GetTextureImage(self.id, level as i32, format.format, format.ty, size as i32, std::ptr::null_mut()),
+                    "Failed to read texture!"
+                ),
+            };
+        }
+        if !supported(GlFeature::TEXTURE_READBACK) {
+            return Err(GlError::Error(
+                "Texture readback unsupported".to_string(),
+                "glGetTexImage isn't available!".to_string()
+            ));
+        }
+
+        // Cube faces have to be read one by one, each into its slice of the pack buffer.
+        let faces = match info.dimension {
+            TextureDimension::CUBE => (0..6).map(|face| gl::TEXTURE_CUBE_MAP_POSITIVE_X + face).collect(),
+            _ => vec![self.target],
+        };
+        let face_size = size / faces.len();
+        for (i, target) in faces.into_iter().enumerate() {
+            let offset = (i * face_size) as *mut std::ffi::c_void;
+            match info.compression {
+                Some(_) => gl_check!(gl::GetCompressedTexImage(target, level as i32, offset), "Failed to read compressed texture!")?,
+                None => gl_check!(gl::GetTexImage(target, level as i32, format.format, format.ty, offset), "Failed to read texture!")?,
+            }
+        }
+
+        Ok(())
+    }
+    /// Pixel unpack buffer used by `stream`, created on first use.
+    pub(crate) fn stream_buffer(&mut self) -> Result<&GlBuffer, GlError> {
+        if self.stream_buffer.is_none() {
+            self.stream_buffer = Some(GlBuffer::new(gl::PIXEL_UNPACK_BUFFER)?);
+        }
+
+        Ok(self.stream_buffer.as_ref().unwrap())
+    }
+    /// Same as `update`, but the bytes go through the stream buffer which is orphaned on every
+    /// call, so the upload doesn't wait for draws still reading the previous contents.
+    /// The stream buffer has to be bound to `GL_PIXEL_UNPACK_BUFFER` beforehand.
+    pub(crate) fn stream(&self, region: &TextureRegion, bytes: &[u8]) -> Result<(), GlError> {
+        let expected = self.validate_region(region)?;
+        if bytes.len() < expected {
+            return Err(GlError::Error(
+                "Texture size mismatch".to_string(),
+                format!("Region {:?} needs {} bytes, got {}!", region, expected, bytes.len())
+            ));
+        }
+
+        let buffer = self.stream_buffer.as_ref().ok_or_else(|| GlError::Error(
+            "No stream buffer".to_string(),
+            "Trying to stream a texture without creating its stream buffer!".to_string()
+        ))?;
+
+        buffer.set_data_full(expected, std::ptr::null(), gl::STREAM_DRAW)?;
+        let mapped = buffer.map_range(0, expected, gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_BUFFER_BIT)?;
+        unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), mapped as *mut u8, expected); }
+        buffer.unmap()?;
+
+        // With an unpack buffer bound the data pointer is an offset into it.
+        self.sub_image(0, region, std::ptr::null())?;
+        self.regenerate_if_needed()
+    }
+    fn regenerate_if_needed(&self) -> Result<(), GlError> {
+        if self.info.is_some_and(|info| info.generate_mips) {
+            self.generate_mipmaps()?;
+        }
+
+        Ok(())
+    }
+    /// Returns the number of bytes the region needs.
+    fn validate_region(&self, region: &TextureRegion) -> Result<usize, GlError> {
+        let info = self.info.ok_or_else(|| GlError::Error(
+            "Texture not loaded".to_string(),
+            "Trying to update a texture that has no storage!".to_string()
+        ))?;
+        
+        let out_of_bounds = || GlError::Error(
+            "Texture region out of bounds".to_string(),
+            format!("{:?} doesn't fit in {}x{}x{}!", region, info.width, info.height, info.depth)
+        );
+        let end_x = region.x.checked_add(region.width).ok_or_else(out_of_bounds)?;
+        let end_y = region.y.checked_add(region.height).ok_or_else(out_of_bounds)?;
+        let end_z = region.z.checked_add(region.depth).ok_or_else(out_of_bounds)?;
+        if end_x > info.width || end_y > info.height || end_z > info.depth {
+            return Err(out_of_bounds());
+        }
+
+        if let Some(compressed) = info.compression {
+            // Partial blocks are only allowed where the region touches the texture edge.
+            let (block_width, block_height) = compressed.block_extent();
+            let aligned = region.x.is_multiple_of(block_width)
+                && region.y.is_multiple_of(block_height)
+                && (region.width.is_multiple_of(block_width) || end_x == info.width)
+                && (region.height.is_multiple_of(block_height) || end_y == info.height);
+            if !aligned {
+                return Err(GlError::Error(
+                    "Texture region not block aligned".to_string(),
+                    format!("{:?} isn't aligned to the {}x{} blocks of {:?}!", region, block_width, block_height, compressed)
+                ));
+            }
+        }
+
+        Ok(region_size(region, &info))
+    }
+    fn sub_image(&self, level: u32, region: &TextureRegion, data: *const std::ffi::c_void) -> Result<(), GlError> {
+        let info = self.info.unwrap();
+        if info.compression.is_some() {
+            return self.compressed_sub_image(level, region, data);
+        }
+
+        let format = GlFormat::new(info.format, info.ty, None)?;
+        set_unpack_alignment(region.width, info.format, info.ty)?;
+
+        let level = level as i32;
+        let (x, y, z) = (region.x as i32, region.y as i32, region.z as i32);
+        let (width, height, depth) = (region.width as i32, region.height as i32, region.depth as i32);
+        
+        if dsa() {
+            // Cube map faces are addressed as layers with DSA.
+            return if self.target == gl::TEXTURE_2D {
+                gl_check!(
+                    gl::TextureSubImage2D(self.id, level, x, y, width, height, format.format, format.ty, data),
+                    "Failed to update texture!"
+                )
+            } else {
+                gl_check!(
+                    gl::TextureSubImage3D(self.id, level, x, y, z, width, height, depth, format.format, format.ty, data),
+                    "Failed to update texture!"
+                )
+            };
+        }
+
+        match info.dimension {
+            TextureDimension::TEXTURE_2D => gl_check!(
+                gl::TexSubImage2D(self.target, level, x, y, width, height, format.format, format.ty, data),
+                "Failed to update texture!"
             ),
-            "Failed to load texture!"
-        )?;
+            TextureDimension::CUBE => {
+                let face_bytes = region_size(&TextureRegion { depth: 1, ..*region }, &info);
+                for face in 0..region.depth {
+                    let face_data = (data as *const u8).wrapping_add(face as usize * face_bytes);
+                    gl_check!(
+                        gl::TexSubImage2D(
+                            gl::TEXTURE_CUBE_MAP_POSITIVE_X + region.z + face,
+                            level,
+                            x,
+                            y,
+                            width,
+                            height,
+                            format.format,
+                            format.ty,
+                            face_data as *const _
+                        ),
+                        "Failed to update cube map face!"
+                    )?;
+                }
 
-        gl_check!(gl::GenerateMipmap(gl::TEXTURE_2D), "Failed to generate mip map!")?;
-        gl_check!(gl::GenerateTextureMipmap(self.id), "Failed to generate mip map for texture!")
+                Ok(())
+            },
+            _ => gl_check!(
+                gl::TexSubImage3D(self.target, level, x, y, z, width, height, depth, format.format, format.ty, data),
+                "Failed to update texture!"
+            ),
+        }
+    }
+    fn compressed_sub_image(&self, level: u32, region: &TextureRegion, data: *const std::ffi::c_void) -> Result<(), GlError> {
+        let info = self.info.unwrap();
+        let format = GlFormat::new(info.format, info.ty, info.compression)?;
+        let size = region_size(region, &info) as i32;
+
+        let level = level as i32;
+        let (x, y, z) = (region.x as i32, region.y as i32, region.z as i32);
+        let (width, height, depth) = (region.width as i32, region.height as i32, region.depth as i32);
+
+        if dsa() {
+            return if self.target == gl::TEXTURE_2D {
+                gl_check!(
+                    gl::CompressedTextureSubImage2D(self.id, level, x, y, width, height, format.internal, size, data),
+                    "Failed to update compressed texture!"
+                )
+            } else {
+                gl_check!(
+                    gl::CompressedTextureSubImage3D(self.id, level, x, y, z, width, height, depth, format.internal, size, data),
+                    "Failed to update compressed texture!"
+                )
+            };
+        }
+
+        match info.dimension {
+            TextureDimension::TEXTURE_2D => gl_check!(
+                gl::CompressedTexSubImage2D(self.target, level, x, y, width, height, format.internal, size, data),
+                "Failed to update compressed texture!"
+            ),
+            TextureDimension::CUBE => {
+                let face_bytes = region_size(&TextureRegion { depth: 1, ..*region }, &info);
+                for face in 0..region.depth {
+                    let face_data = (data as *const u8).wrapping_add(face as usize * face_bytes);
+                    gl_check!(
+                        gl::CompressedTexSubImage2D(
+                            gl::TEXTURE_CUBE_MAP_POSITIVE_X + region.z + face,
+                            level,
+                            x,
+                            y,
+                            width,
+                            height,
+                            format.internal,
+                            face_bytes as i32,
+                            face_data as *const _
+                        ),
+                        "Failed to update compressed cube map face!"
+                    )?;
+                }
+
+                Ok(())
+            },
+            _ => gl_check!(
+                gl::CompressedTexSubImage3D(self.target, level, x, y, z, width, height, depth, format.internal, size, data),
+                "Failed to update compressed texture!"
+            ),
+        }
     }
 }
 impl Drop for GlTexture {
@@ -46,13 +572,366 @@ impl Drop for GlTexture {
     }
 }
 
+/// (internal format, upload format, upload type)
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GlFormat {
+    pub(crate) internal: gl::types::GLenum,
+    pub(crate) format: gl::types::GLenum,
+    pub(crate) ty: gl::types::GLenum,
+}
+impl GlFormat {
+    /// With `compression` only `internal` is meaningful.
+    pub(crate) fn new(
+        tex_format: TextureFormat,
+        tex_type: TextureType,
+        compression: Option<CompressedFormat>
+    ) -> Result<Self, GlError>
+    {
+        let internal = match compression {
+            Some(compressed) => compressed_format_to_opengl(compressed, tex_format.is_srgb())?,
+            None => tex_internal_format_to_opengl(tex_format, tex_type)?,
+        };
+
+        Ok(Self {
+            internal,
+            format: tex_format_to_opengl(tex_format),
+            ty: tex_type_to_opengl(tex_type),
+        })
+    }
+}
+
+/// (levels allocated, whether levels past the base are generated)
+fn mip_levels(texture: &impl LgTexture) -> (u32, bool) {
+    let full_chain = full_mip_count(texture.width(), texture.height(), texture.depth(), texture.dimension());
+    // Integer and depth textures can't be filtered, so mips are never generated for them.
+    // Compressed formats can't be rendered to, which generating needs.
+    let filterable = !texture.texture_format().is_integer()
+        && !texture.texture_format().is_depth()
+        && texture.compression().is_none();
+
+    match texture.mip_mode() {
+        MipMode::GENERATE if filterable => (full_chain, true),
+        MipMode::GENERATE_LEVELS if filterable => (texture.mip_level().clamp(1, full_chain), true),
+        MipMode::PROVIDED => (texture.mip_level().clamp(1, full_chain), false),
+        _ => (1, false),
+    }
+}
+fn set_unpack_alignment(width: u32, format: TextureFormat, ty: TextureType) -> Result<(), GlError> {
+    let row_bytes = width as usize * bytes_per_pixel(format, ty);
+    let alignment = if row_bytes.is_multiple_of(4) { 4 } else { 1 };
+    
+    gl_check!(gl::PixelStorei(gl::UNPACK_ALIGNMENT, alignment), "Failed to set unpack alignment!")
+}
+fn region_size(region: &TextureRegion, info: &GlTextureInfo) -> usize {
+    image_size(region.width, region.height, region.depth, info.format, info.ty, info.compression)
+}
+/// Everything `GlTexture::load` checks before touching GL: a known format and enough bytes for the provided levels.
+pub(crate) fn validate_texture(texture: &impl LgTexture) -> Result<GlFormat, GlError> {
+    let format = GlFormat::new(texture.texture_format(), texture.texture_type(), texture.compression())?;
+    let (levels, _) = mip_levels(texture);
+    let provided_levels = if texture.mip_mode() == MipMode::PROVIDED { levels } else { 1 };
+    validate_size(texture, provided_levels)?;
+
+    Ok(format)
+}
+/// `levels` is how many mip levels `bytes` has to hold.
+fn validate_size(texture: &impl LgTexture, levels: u32) -> Result<(), GlError> {
+    if texture.dimension() == TextureDimension::CUBE && texture.depth() != 6 {
+        return Err(GlError::Error(
+            "Texture size mismatch".to_string(),
+            format!("Cube maps need 6 faces, got {}!", texture.depth())
+        ));
+    }
+    if texture.dimension() == TextureDimension::CUBE_ARRAY && !texture.depth().is_multiple_of(6) {
+        return Err(GlError::Error(
+            "Texture size mismatch".to_string(),
+            format!("Cube map arrays need a multiple of 6 faces, got {}!", texture.depth())
+        ));
+    }
+    let expected: usize = (0..levels)
+        .map(|level| {
+            let (width, height, depth) = mip_extent(texture.width(), texture.height(), texture.depth(), texture.dimension(), level);
+            image_size(width, height, depth, texture.texture_format(), texture.texture_type(), texture.compression())
+        })
+        .sum();
+
+    if texture.bytes().len() < expected {
+        return Err(GlError::Error(
+            "Texture size mismatch".to_string(),
+            format!(
+                "{}x{}x{} {:?}/{:?}/{:?} with {} level(s) needs {} bytes, got {}!",
+                texture.width(),
+                texture.height(),
+                texture.depth(),
+                texture.texture_format(),
+                texture.texture_type(),
+                texture.compression(),
+                levels,
+                expected,
+                texture.bytes().len()
+            )
+        ));
+    }
+
+    Ok(())
+}
+
+fn dimension_to_opengl(dimension: TextureDimension) -> gl::types::GLenum {
+    match dimension {
+        TextureDimension::TEXTURE_2D => gl::TEXTURE_2D,
+        TextureDimension::TEXTURE_2D_ARRAY => gl::TEXTURE_2D_ARRAY,
+        TextureDimension::CUBE => gl::TEXTURE_CUBE_MAP,
+        TextureDimension::CUBE_ARRAY => gl::TEXTURE_CUBE_MAP_ARRAY,
+        TextureDimension::TEXTURE_3D => gl::TEXTURE_3D,
+    }
+}
 fn tex_type_to_opengl(tex_type: TextureType) -> gl::types::GLenum {
     match tex_type {
         TextureType::UNSIGNED_BYTE => gl::UNSIGNED_BYTE,
+        TextureType::BYTE => gl::BYTE,
+        TextureType::UNSIGNED_SHORT => gl::UNSIGNED_SHORT,
+        TextureType::SHORT => gl::SHORT,
+        TextureType::UNSIGNED_INT => gl::UNSIGNED_INT,
+        TextureType::INT => gl::INT,
+        TextureType::HALF_FLOAT => gl::HALF_FLOAT,
+        TextureType::FLOAT => gl::FLOAT,
+        TextureType::UNSIGNED_INT_24_8 => gl::UNSIGNED_INT_24_8,
     }
 }
 fn tex_format_to_opengl(tex_format: TextureFormat) -> gl::types::GLenum {
     match tex_format {
-        TextureFormat::RGBA => gl::RGBA,
+        TextureFormat::RGBA | TextureFormat::SRGB_ALPHA => gl::RGBA,
+        TextureFormat::R => gl::RED,
+        TextureFormat::RG => gl::RG,
+        TextureFormat::RGB | TextureFormat::SRGB => gl::RGB,
+        TextureFormat::R_INTEGER => gl::RED_INTEGER,
+        TextureFormat::RG_INTEGER => gl::RG_INTEGER,
+        TextureFormat::RGBA_INTEGER => gl::RGBA_INTEGER,
+        TextureFormat::DEPTH => gl::DEPTH_COMPONENT,
+        TextureFormat::DEPTH_STENCIL => gl::DEPTH_STENCIL,
+    }
+}
+pub(crate) fn tex_internal_format_to_opengl(tex_format: TextureFormat, tex_type: TextureType) -> Result<gl::types::GLenum, GlError> {
+    use TextureFormat as F;
+    use TextureType as T;
+
+    Ok(match (tex_format, tex_type) {
+        (F::R, T::UNSIGNED_BYTE) => gl::R8,
+        (F::R, T::BYTE) => gl::R8_SNORM,
+        (F::R, T::UNSIGNED_SHORT) => gl::R16,
+        (F::R, T::HALF_FLOAT) => gl::R16F,
+        (F::R, T::FLOAT) => gl::R32F,
+        (F::RG, T::UNSIGNED_BYTE) => gl::RG8,
+        (F::RG, T::BYTE) => gl::RG8_SNORM,
+        (F::RG, T::UNSIGNED_SHORT) => gl::RG16,
+        (F::RG, T::HALF_FLOAT) => gl::RG16F,
+        (F::RG, T::FLOAT) => gl::RG32F,
+        (F::RGB, T::UNSIGNED_BYTE) => gl::RGB8,
+        (F::RGB, T::BYTE) => gl::RGB8_SNORM,
+        (F::RGB, T::UNSIGNED_SHORT) => gl::RGB16,
+        (F::RGB, T::HALF_FLOAT) => gl::RGB16F,
+        (F::RGB, T::FLOAT) => gl::RGB32F,
+        (F::RGBA, T::UNSIGNED_BYTE) => gl::RGBA8,
+        (F::RGBA, T::BYTE) => gl::RGBA8_SNORM,
+        (F::RGBA, T::UNSIGNED_SHORT) => gl::RGBA16,
+        (F::RGBA, T::HALF_FLOAT) => gl::RGBA16F,
+        (F::RGBA, T::FLOAT) => gl::RGBA32F,
+        (F::SRGB, T::UNSIGNED_BYTE) => gl::SRGB8,
+        (F::SRGB_ALPHA, T::UNSIGNED_BYTE) => gl::SRGB8_ALPHA8,
+        (F::R_INTEGER, T::UNSIGNED_BYTE) => gl::R8UI,
+        (F::R_INTEGER, T::BYTE) => gl::R8I,
+        (F::R_INTEGER, T::UNSIGNED_SHORT) => gl::R16UI,
+        (F::R_INTEGER, T::SHORT) => gl::R16I,
+        (F::R_INTEGER, T::UNSIGNED_INT) => gl::R32UI,
+        (F::R_INTEGER, T::INT) => gl::R32I,
+        (F::RG_INTEGER, T::UNSIGNED_BYTE) => gl::RG8UI,
+        (F::RG_INTEGER, T::BYTE) => gl::RG8I,
+        (F::RG_INTEGER, T::UNSIGNED_SHORT) => gl::RG16UI,
+        (F::RG_INTEGER, T::SHORT) => gl::RG16I,
+        (F::RG_INTEGER, T::UNSIGNED_INT) => gl::RG32UI,
+        (F::RG_INTEGER, T::INT) => gl::RG32I,
+        (F::RGBA_INTEGER, T::UNSIGNED_BYTE) => gl::RGBA8UI,
+        (F::RGBA_INTEGER, T::BYTE) => gl::RGBA8I,
+        (F::RGBA_INTEGER, T::UNSIGNED_SHORT) => gl::RGBA16UI,
+        (F::RGBA_INTEGER, T::SHORT) => gl::RGBA16I,
+        (F::RGBA_INTEGER, T::UNSIGNED_INT) => gl::RGBA32UI,
+        (F::RGBA_INTEGER, T::INT) => gl::RGBA32I,
+        (F::DEPTH, T::UNSIGNED_SHORT) => gl::DEPTH_COMPONENT16,
+        (F::DEPTH, T::UNSIGNED_INT) => gl::DEPTH_COMPONENT24,
+        (F::DEPTH, T::FLOAT) => gl::DEPTH_COMPONENT32F,
+        (F::DEPTH_STENCIL, T::UNSIGNED_INT_24_8) => gl::DEPTH24_STENCIL8,
+        _ => return Err(GlError::Error(
+            "Unsupported texture format".to_string(),
+            format!("{:?} can't be used with {:?}!", tex_format, tex_type)
+        )),
+    })
+}
+// S3TC and ASTC are extensions, the gl crate only has core enums.
+const COMPRESSED_RGB_S3TC_DXT1: gl::types::GLenum = 0x83F0;
+const COMPRESSED_RGBA_S3TC_DXT1: gl::types::GLenum = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT3: gl::types::GLenum = 0x83F2;
+const COMPRESSED_RGBA_S3TC_DXT5: gl::types::GLenum = 0x83F3;
+const COMPRESSED_SRGB_S3TC_DXT1: gl::types::GLenum = 0x8C4C;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT1: gl::types::GLenum = 0x8C4D;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT3: gl::types::GLenum = 0x8C4E;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT5: gl::types::GLenum = 0x8C4F;
+/// ASTC enums are laid out in `CompressedFormat` order starting at these.
+const COMPRESSED_RGBA_ASTC_4X4: gl::types::GLenum = 0x93B0;
+const COMPRESSED_SRGB8_ALPHA8_ASTC_4X4: gl::types::GLenum = 0x93D0;
+
+pub(crate) fn compressed_format_to_opengl(compressed: CompressedFormat, srgb: bool) -> Result<gl::types::GLenum, GlError> {
+    use CompressedFormat as C;
+
+    if srgb && !compressed.has_srgb() {
+        return Err(GlError::Error(
+            "Unsupported texture format".to_string(),
+            format!("{:?} has no sRGB variant!", compressed)
+        ));
+    }
+
+    let astc_index = match compressed {
+        C::ASTC_4x4 => Some(0),
+        C::ASTC_5x4 => Some(1),
+        C::ASTC_5x5 => Some(2),
+        C::ASTC_6x5 => Some(3),
+        C::ASTC_6x6 => Some(4),
+        C::ASTC_8x5 => Some(5),
+        C::ASTC_8x6 => Some(6),
+        C::ASTC_8x8 => Some(7),
+        C::ASTC_10x5 => Some(8),
+        C::ASTC_10x6 => Some(9),
+        C::ASTC_10x8 => Some(10),
+        C::ASTC_10x10 => Some(11),
+        C::ASTC_12x10 => Some(12),
+        C::ASTC_12x12 => Some(13),
+        _ => None,
+    };
+    if let Some(astc_index) = astc_index {
+        let base = if srgb { COMPRESSED_SRGB8_ALPHA8_ASTC_4X4 } else { COMPRESSED_RGBA_ASTC_4X4 };
+        return Ok(base + astc_index);
+    }
+
+    Ok(match (compressed, srgb) {
+        (C::BC1, false) => COMPRESSED_RGB_S3TC_DXT1,
+        (C::BC1, true) => COMPRESSED_SRGB_S3TC_DXT1,
+        (C::BC1_ALPHA, false) => COMPRESSED_RGBA_S3TC_DXT1,
+        (C::BC1_ALPHA, true) => COMPRESSED_SRGB_ALPHA_S3TC_DXT1,
+        (C::BC2, false) => COMPRESSED_RGBA_S3TC_DXT3,
+        (C::BC2, true) => COMPRESSED_SRGB_ALPHA_S3TC_DXT3,
+        (C::BC3, false) => COMPRESSED_RGBA_S3TC_DXT5,
+        (C::BC3, true) => COMPRESSED_SRGB_ALPHA_S3TC_DXT5,
+        (C::BC4, _) => gl::COMPRESSED_RED_RGTC1,
+        (C::BC4_SIGNED, _) => gl::COMPRESSED_SIGNED_RED_RGTC1,
+        (C::BC5, _) => gl::COMPRESSED_RG_RGTC2,
+        (C::BC5_SIGNED, _) => gl::COMPRESSED_SIGNED_RG_RGTC2,
+        (C::BC6H, _) => gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+        (C::BC6H_SIGNED, _) => gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT,
+        (C::BC7, false) => gl::COMPRESSED_RGBA_BPTC_UNORM,
+        (C::BC7, true) => gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+        (C::ETC2_RGB, false) => gl::COMPRESSED_RGB8_ETC2,
+        (C::ETC2_RGB, true) => gl::COMPRESSED_SRGB8_ETC2,
+        (C::ETC2_RGB_A1, false) => gl::COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+        (C::ETC2_RGB_A1, true) => gl::COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+        (C::ETC2_RGBA, false) => gl::COMPRESSED_RGBA8_ETC2_EAC,
+        (C::ETC2_RGBA, true) => gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
+        (C::EAC_R, _) => gl::COMPRESSED_R11_EAC,
+        (C::EAC_R_SIGNED, _) => gl::COMPRESSED_SIGNED_R11_EAC,
+        (C::EAC_RG, _) => gl::COMPRESSED_RG11_EAC,
+        (C::EAC_RG_SIGNED, _) => gl::COMPRESSED_SIGNED_RG11_EAC,
+        _ => unreachable!("ASTC handled above"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestTexture {
+        width: u32,
+        height: u32,
+        depth: u32,
+        dimension: TextureDimension,
+        format: TextureFormat,
+        ty: TextureType,
+        compression: Option<CompressedFormat>,
+        mip_mode: MipMode,
+        mip_level: u32,
+        bytes: Vec<u8>,
+    }
+    impl TestTexture {
+        fn rgba8(width: u32, height: u32, bytes: usize) -> Self {
+            Self {
+                width,
+                height,
+                depth: 1,
+                dimension: TextureDimension::TEXTURE_2D,
+                format: TextureFormat::RGBA,
+                ty: TextureType::UNSIGNED_BYTE,
+                compression: None,
+                mip_mode: MipMode::NONE,
+                mip_level: 1,
+                bytes: vec![0; bytes],
+            }
+        }
+    }
+    impl LgTexture for TestTexture {
+        fn width(&self) -> u32 { self.width }
+        fn height(&self) -> u32 { self.height }
+        fn bytes(&self) -> &[u8] { &self.bytes }
+        fn size(&self) -> u64 { self.bytes.len() as u64 }
+        fn mip_level(&self) -> u32 { self.mip_level }
+        fn texture_type(&self) -> TextureType { self.ty }
+        fn texture_format(&self) -> TextureFormat { self.format }
+        fn compression(&self) -> Option<CompressedFormat> { self.compression }
+        fn mip_mode(&self) -> MipMode { self.mip_mode }
+        fn dimension(&self) -> TextureDimension { self.dimension }
+        fn depth(&self) -> u32 { self.depth }
+    }
+
+    #[test]
+    fn size_validation() {
+        assert!(validate_size(&TestTexture::rgba8(4, 4, 64), 1).is_ok());
+        assert!(validate_size(&TestTexture::rgba8(4, 4, 63), 1).is_err());
+        // 4x4 + 2x2 + 1x1
+        assert!(validate_size(&TestTexture::rgba8(4, 4, 84), 3).is_ok());
+        assert!(validate_size(&TestTexture::rgba8(4, 4, 83), 3).is_err());
+
+        let half = TestTexture { ty: TextureType::HALF_FLOAT, format: TextureFormat::RG, ..TestTexture::rgba8(2, 2, 16) };
+        assert!(validate_size(&half, 1).is_ok());
+    }
+
+    #[test]
+    fn compressed_size_validation() {
+        let bc3 = TestTexture { compression: Some(CompressedFormat::BC3), ..TestTexture::rgba8(6, 6, 64) };
+        assert!(validate_size(&bc3, 1).is_ok());
+
+        let short = TestTexture { bytes: vec![0; 63], ..bc3 };
+        assert!(validate_size(&short, 1).is_err());
+    }
+
+    #[test]
+    fn mip_level_counts() {
+        let generated = TestTexture { mip_mode: MipMode::GENERATE, ..TestTexture::rgba8(16, 4, 0) };
+        assert_eq!(mip_levels(&generated), (5, true));
+
+        let limited = TestTexture { mip_mode: MipMode::GENERATE_LEVELS, mip_level: 2, ..TestTexture::rgba8(16, 4, 0) };
+        assert_eq!(mip_levels(&limited), (2, true));
+
+        let provided = TestTexture { mip_mode: MipMode::PROVIDED, mip_level: 9, ..TestTexture::rgba8(16, 4, 0) };
+        assert_eq!(mip_levels(&provided), (5, false));
+
+        // Integer textures can't be filtered, so nothing is generated for them.
+        let integer = TestTexture { mip_mode: MipMode::GENERATE, format: TextureFormat::RGBA_INTEGER, ..TestTexture::rgba8(16, 4, 0) };
+        assert_eq!(mip_levels(&integer), (1, false));
+    }
+
+    #[test]
+    fn internal_formats() {
+        assert_eq!(tex_internal_format_to_opengl(TextureFormat::RGBA, TextureType::HALF_FLOAT).unwrap(), gl::RGBA16F);
+        assert_eq!(tex_internal_format_to_opengl(TextureFormat::RG_INTEGER, TextureType::INT).unwrap(), gl::RG32I);
+        assert_eq!(tex_internal_format_to_opengl(TextureFormat::SRGB_ALPHA, TextureType::UNSIGNED_BYTE).unwrap(), gl::SRGB8_ALPHA8);
+        assert!(tex_internal_format_to_opengl(TextureFormat::DEPTH, TextureType::UNSIGNED_BYTE).is_err());
+        assert!(tex_internal_format_to_opengl(TextureFormat::SRGB_ALPHA, TextureType::FLOAT).is_err());
+        assert!(tex_internal_format_to_opengl(TextureFormat::RGBA, TextureType::UNSIGNED_INT_24_8).is_err());
     }
 }
\ No newline at end of file
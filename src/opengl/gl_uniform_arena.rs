@@ -0,0 +1,180 @@
+use std::{collections::HashMap, hash::Hash};
+
+use super::{gl_buffer::GlBuffer, gl_capabilities::dsa, gl_state::GlStateCache, GlError};
+
+/// Uniforms bigger than this get a page of their own.
+pub(crate) const ARENA_PAGE_SIZE: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct GlArenaSlot {
+    pub(crate) page: usize,
+    /// In bytes from the start of the page.
+    pub(crate) offset: usize,
+    pub(crate) size: usize,
+    /// Size the slot was carved out with, a reused slot can hold less.
+    capacity: usize,
+}
+
+/// Packs uniform blocks of many keys into a few large buffers, each key owns an aligned range
+/// that gets bound with `glBindBufferRange`.
+#[derive(Debug)]
+pub(crate) struct GlUniformArena<K: Eq + Hash> {
+    /// (buffer, size, used bytes)
+    pages: Vec<(GlBuffer, usize, usize)>,
+    slots: HashMap<K, GlArenaSlot>,
+    free: Vec<GlArenaSlot>,
+}
+impl<K: Eq + Hash> Default for GlUniformArena<K> {
+    fn default() -> Self {
+        Self {
+            pages: Vec::new(),
+            slots: HashMap::new(),
+            free: Vec::new(),
+        }
+    }
+}
+impl<K: Eq + Hash> GlUniformArena<K> {
+    /// Returns whether the key already had a slot of this size, otherwise a new one is handed out.
+    pub(crate) fn slot(
+        &mut self,
+        key: K,
+        size: usize,
+        align: usize,
+        state_cache: &mut GlStateCache
+    ) -> Result<(bool, GlArenaSlot), GlError>
+    {
+        let align = align.max(1);
+        if let Some(slot) = self.slots.get(&key) {
+            if slot.size == size && slot.offset.is_multiple_of(align) {
+                return Ok((true, *slot));
+            }
+        }
+        if let Some(old) = self.slots.remove(&key) {
+            self.free.push(old);
+        }
+
+        let reused = self.free.iter().position(|slot| slot.capacity >= size && slot.offset.is_multiple_of(align));
+        let slot = match reused {
+            Some(index) => GlArenaSlot { size, ..self.free.swap_remove(index) },
+            None => self.carve(size, align, state_cache)?,
+        };
+        self.slots.insert(key, slot);
+
+        Ok((false, slot))
+    }
+    pub(crate) fn page(&self, page: usize) -> &GlBuffer {
+        &self.pages[page].0
+    }
+    /// The range is reused by later uniforms, returns whether the key had one.
+    pub(crate) fn release(&mut self, key: &K) -> bool {
+        match self.slots.remove(key) {
+            Some(slot) => {
+                self.free.push(slot);
+                true
+            },
+            None => false,
+        }
+    }
+    /// Deleting the pages unbinds them, the cache is told so it doesn't skip rebinding recycled names.
+    pub(crate) fn clear(&mut self, state_cache: &mut GlStateCache) {
+        for (buffer, _, _) in &self.pages {
+            state_cache.forget_buffer(buffer.id());
+        }
+        self.pages.clear();
+        self.slots.clear();
+        self.free.clear();
+    }
+
+    fn carve(&mut self, size: usize, align: usize, state_cache: &mut GlStateCache) -> Result<GlArenaSlot, GlError> {
+        let fits = self.pages.iter().position(|(_, page_size, used)| align_up(*used, align) + size <= *page_size);
+        let page = match fits {
+            Some(page) => page,
+            None => {
+                let buffer = GlBuffer::new(gl::UNIFORM_BUFFER)?;
+                if !dsa() {
+                    state_cache.bind_buffer(gl::UNIFORM_BUFFER, buffer.id())?;
+                }
+                let page_size = size.max(ARENA_PAGE_SIZE);
+                buffer.set_data_full(page_size, std::ptr::null(), gl::DYNAMIC_DRAW)?;
+                self.pages.push((buffer, page_size, 0));
+                self.pages.len() - 1
+            },
+        };
+
+        let used = &mut self.pages[page].2;
+        let offset = align_up(*used, align);
+        *used = offset + size;
+
+        Ok(GlArenaSlot { page, offset, size, capacity: size })
+    }
+}
+
+fn align_up(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn free_slot(page: usize, offset: usize, capacity: usize) -> GlArenaSlot {
+        GlArenaSlot { page, offset, size: capacity, capacity }
+    }
+
+    #[test]
+    fn offsets_round_up_to_alignment() {
+        assert_eq!(align_up(0, 256), 0);
+        assert_eq!(align_up(1, 256), 256);
+        assert_eq!(align_up(256, 256), 256);
+        assert_eq!(align_up(300, 64), 320);
+        assert_eq!(align_up(7, 1), 7);
+    }
+
+    #[test]
+    fn same_size_keeps_its_slot() {
+        let mut arena = GlUniformArena::default();
+        let mut cache = GlStateCache::default();
+        arena.free.push(free_slot(0, 512, 128));
+
+        let (kept, slot) = arena.slot("a", 64, 256, &mut cache).unwrap();
+        assert!(!kept);
+        assert_eq!((slot.offset, slot.size), (512, 64));
+        assert_eq!(arena.slot("a", 64, 256, &mut cache).unwrap(), (true, slot));
+    }
+
+    #[test]
+    fn released_slots_are_reused() {
+        let mut arena = GlUniformArena::default();
+        let mut cache = GlStateCache::default();
+        arena.free.push(free_slot(0, 0, 256));
+
+        let (_, first) = arena.slot("a", 200, 256, &mut cache).unwrap();
+        assert!(arena.release(&"a"));
+        assert!(!arena.release(&"a"));
+
+        // A smaller block fits into the freed range, its capacity stays the original size.
+        let (_, second) = arena.slot("b", 100, 256, &mut cache).unwrap();
+        assert_eq!((second.page, second.offset, second.size), (first.page, first.offset, 100));
+        assert!(arena.release(&"b"));
+        let (_, third) = arena.slot("c", 256, 256, &mut cache).unwrap();
+        assert_eq!(third.offset, first.offset);
+    }
+
+    #[test]
+    fn reuse_respects_alignment() {
+        let mut arena = GlUniformArena::default();
+        let mut cache = GlStateCache::default();
+        arena.free.push(free_slot(0, 64, 256));
+        arena.free.push(free_slot(0, 512, 256));
+
+        let (_, slot) = arena.slot("a", 64, 256, &mut cache).unwrap();
+        assert_eq!(slot.offset, 512);
+
+        // Growing an existing key moves it, the old range goes to the free list.
+        arena.free.push(free_slot(1, 0, 1024));
+        let (kept, grown) = arena.slot("a", 512, 256, &mut cache).unwrap();
+        assert!(!kept);
+        assert_eq!((grown.page, grown.offset), (1, 0));
+        assert!(arena.free.iter().any(|free| free.offset == 512 && free.capacity == 256));
+    }
+}
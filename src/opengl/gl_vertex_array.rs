@@ -1,65 +1,429 @@
 use crate::gl_check;
-use super::{gl_buffer::GlBuffer, GlError};
+use crate::renderer_core::lg_vertex::{AttributeKind, VertexAttribute, VertexComponent, VertexLayout};
+use super::{gl_buffer::GlBuffer, gl_capabilities::{dsa, supported, GlFeature}, gl_state::GlStateCache, GlError};
+
+/// Buffer that remembers its size and usage, so writes past the end can reallocate it.
+#[derive(Debug)]
+struct GlMeshBuffer {
+    buffer: GlBuffer,
+    size: usize,
+    usage: gl::types::GLenum,
+}
+impl GlMeshBuffer {
+    fn new(target: gl::types::GLenum) -> Result<Self, GlError> {
+        Ok(Self {
+            buffer: GlBuffer::new(target)?,
+            size: 0,
+            usage: gl::STATIC_DRAW,
+        })
+    }
+    /// Without DSA the buffer has to be bound to its target beforehand.
+    fn replace<D>(&mut self, data: &[D], usage: gl::types::GLenum) -> Result<(), GlError> {
+        self.buffer.set_data(data, usage)?;
+        self.size = std::mem::size_of_val(data);
+        self.usage = usage;
+
+        Ok(())
+    }
+    /// Without DSA the buffer has to be bound to its target beforehand, after growing the new buffer is bound instead.
+    /// Growing at least doubles the size and keeps the old contents, returns whether it happened.
+    fn write<D>(&mut self, offset: usize, data: &[D], state_cache: &mut GlStateCache) -> Result<bool, GlError> {
+        let end = offset + std::mem::size_of_val(data);
+        let grow = end > self.size;
+
+        if grow {
+            let size = end.max(self.size * 2);
+            let buffer = GlBuffer::new(self.buffer.target())?;
+            if !dsa() {
+                state_cache.bind_buffer(gl::COPY_READ_BUFFER, self.buffer.id())?;
+                state_cache.bind_buffer(buffer.target(), buffer.id())?;
+            }
+            buffer.set_data_full(size, std::ptr::null(), self.usage)?;
+            if self.size > 0 {
+                buffer.copy_from(&self.buffer, 0, self.size)?;
+            }
+
+            let old = std::mem::replace(&mut self.buffer, buffer);
+            state_cache.forget_buffer(old.id());
+            self.size = size;
+        }
+        self.buffer.sub_data(offset, data)?;
+
+        Ok(grow)
+    }
+}
+
+#[derive(Debug)]
+struct GlVertexStream {
+    buffer: GlMeshBuffer,
+    layout: VertexLayout,
+    divisor: gl::types::GLuint,
+    enabled: bool,
+    /// Fed from a buffer the stream doesn't own.
+    transient: bool,
+}
 
 #[derive(Debug)]
 pub struct GlVertexArray {
     id: gl::types::GLuint,
-    vertex_buffer: GlBuffer,
-    index_buffer: GlBuffer,
+    /// Indexed by stream, which is also the binding index with DSA.
+    streams: Vec<Option<GlVertexStream>>,
+    index_buffer: GlMeshBuffer,
+    index_count: usize,
 }
 impl GlVertexArray {
     pub(crate) fn new() -> Result<Self, GlError> {
         let mut id = 0;
-        gl_check!(gl::GenVertexArrays(1, &mut id), "Failed to generate vertex array!")?;
+        let index_buffer = GlMeshBuffer::new(gl::ELEMENT_ARRAY_BUFFER)?;
+
+        if dsa() {
+            gl_check!(gl::CreateVertexArrays(1, &mut id), "Failed to generate vertex array!")?;
+            gl_check!(gl::VertexArrayElementBuffer(id, index_buffer.buffer.id()), "Failed to set element buffer!")?;
+        } else {
+            gl_check!(gl::GenVertexArrays(1, &mut id), "Failed to generate vertex array!")?;
+        }
         
         Ok(Self { 
             id, 
-            vertex_buffer: GlBuffer::new(gl::ARRAY_BUFFER)?,
-            index_buffer: GlBuffer::new(gl::ELEMENT_ARRAY_BUFFER)?,
+            streams: Vec::new(),
+            index_buffer,
+            index_count: 0,
         })
     }
-    pub(crate) fn vertex_buffer(&self) -> &GlBuffer {
-        &self.vertex_buffer
+    pub(crate) fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+    /// Creates the stream's buffer if it doesn't exist yet, returns whether it did.
+    pub(crate) fn create_stream(&mut self, stream: u32) -> Result<(bool, &GlBuffer), GlError> {
+        let index = stream as usize;
+        if self.streams.len() <= index {
+            self.streams.resize_with(index + 1, || None);
+        }
+
+        let present = self.streams[index].is_some();
+        if !present {
+            self.streams[index] = Some(GlVertexStream {
+                buffer: GlMeshBuffer::new(gl::ARRAY_BUFFER)?,
+                layout: VertexLayout { stride: 0, attributes: Vec::new() },
+                divisor: 0,
+                enabled: true,
+                transient: false,
+            });
+        }
+
+        Ok((present, &self.streams[index].as_ref().unwrap().buffer.buffer))
+    }
+    pub(crate) fn stream_buffer(&self, stream: u32) -> Option<&GlBuffer> {
+        self.get_stream(stream).ok().map(|stream| &stream.buffer.buffer)
+    }
+    pub(crate) fn stream_layout(&self, stream: u32) -> Option<&VertexLayout> {
+        self.get_stream(stream).ok().map(|stream| &stream.layout)
+    }
+    /// Highest location used by any stream but `except`.
+    pub(crate) fn last_location(&self, except: u32) -> Option<gl::types::GLuint> {
+        self.streams.iter()
+            .enumerate()
+            .filter(|(index, _)| *index != except as usize)
+            .filter_map(|(_, stream)| stream.as_ref())
+            .flat_map(|stream| stream.layout.attributes.iter().map(|attribute| attribute.location))
+            .max()
+    }
+    /// Without DSA the stream's buffer has to be bound to `GL_ARRAY_BUFFER` beforehand.
+    pub(crate) fn replace_stream<D>(&mut self, stream: u32, data: &[D], usage: gl::types::GLenum) -> Result<(), GlError> {
+        self.get_stream_mut(stream)?.buffer.replace(data, usage)
+    }
+    /// Without DSA the stream's buffer has to be bound to `GL_ARRAY_BUFFER` beforehand.
+    /// `offset` is in bytes, writing past the end grows the buffer.
+    pub(crate) fn write_stream<D>(
+        &mut self,
+        stream: u32,
+        offset: usize,
+        data: &[D],
+        state_cache: &mut GlStateCache
+    ) -> Result<(), GlError> 
+    {
+        let vertex_stream = self.get_stream_mut(stream)?;
+        if vertex_stream.transient {
+            return Err(GlError::Error(
+                "Transient vertex stream".to_string(),
+                format!("Stream {} is fed from the ring buffer, replace it before updating it!", stream)
+            ));
+        }
+        if vertex_stream.buffer.write(offset, data, state_cache)? {
+            // The attributes still point at the old buffer.
+            let vertex_stream = self.get_stream(stream)?;
+            for attribute in &vertex_stream.layout.attributes {
+                self.set_attribute(vertex_stream.buffer.buffer.id(), 0, stream, attribute, vertex_stream.layout.stride, vertex_stream.divisor)?;
+            }
+        }
+
+        Ok(())
+    }
+    /// Without DSA the stream's buffer has to be bound to `GL_ARRAY_BUFFER` beforehand.
+    /// Fails if another stream already feeds one of the layout's locations.
+    pub(crate) fn set_stream_layout(&mut self, stream: u32, layout: &VertexLayout, divisor: gl::types::GLuint) -> Result<(), GlError> {
+        let buffer = self.get_stream(stream)?.buffer.buffer.id();
+        self.point_stream(stream, buffer, 0, layout, divisor)?;
+        self.get_stream_mut(stream)?.transient = false;
+
+        Ok(())
+    }
+    /// Feeds the stream from a range of a buffer it doesn't own, until its layout is set again.
+    /// Without DSA `buffer` has to be bound to `GL_ARRAY_BUFFER` beforehand.
+    pub(crate) fn set_stream_range(
+        &mut self,
+        stream: u32,
+        buffer: gl::types::GLuint,
+        offset: usize,
+        layout: &VertexLayout,
+        divisor: gl::types::GLuint
+    ) -> Result<(), GlError> 
+    {
+        self.create_stream(stream)?;
+        self.point_stream(stream, buffer, offset, layout, divisor)?;
+        self.get_stream_mut(stream)?.transient = true;
+
+        Ok(())
+    }
+    fn point_stream(
+        &mut self,
+        stream: u32,
+        buffer: gl::types::GLuint,
+        offset: usize,
+        layout: &VertexLayout,
+        divisor: gl::types::GLuint
+    ) -> Result<(), GlError> 
+    {
+        for (index, other) in self.streams.iter().enumerate() {
+            let Some(other) = other.as_ref().filter(|_| index != stream as usize) else { continue; };
+            let used = |location| other.layout.attributes.iter().any(|attribute| attribute.location == location);
+            if let Some(attribute) = layout.attributes.iter().find(|attribute| used(attribute.location)) {
+                return Err(GlError::Error(
+                    "Vertex location conflict".to_string(),
+                    format!("Location {} of stream {} is already used by stream {}!", attribute.location, stream, index)
+                ));
+            }
+        }
+
+        // Locations the old layout fed would keep reading from this stream's buffer.
+        for location in stale_locations(&self.get_stream(stream)?.layout, layout) {
+            set_location_enabled(self.id, location, false)?;
+        }
+        for attribute in &layout.attributes {
+            self.set_attribute(buffer, offset, stream, attribute, layout.stride, divisor)?;
+        }
+
+        let vertex_stream = self.get_stream_mut(stream)?;
+        vertex_stream.layout = layout.clone();
+        vertex_stream.divisor = divisor;
+        vertex_stream.enabled = true;
+        
+        Ok(())
+    }
+    /// Without DSA the vertex array has to be bound beforehand.
+    pub(crate) fn set_stream_enabled(&mut self, stream: u32, enabled: bool) -> Result<(), GlError> {
+        let id = self.id;
+        let vertex_stream = self.get_stream_mut(stream)?;
+        if vertex_stream.enabled == enabled {
+            return Ok(());
+        }
+
+        for attribute in &vertex_stream.layout.attributes {
+            set_location_enabled(id, attribute.location, enabled)?;
+        }
+        vertex_stream.enabled = enabled;
+
+        Ok(())
     }
     pub(crate) fn index_buffer(&self) -> &GlBuffer {
-        &self.index_buffer
+        &self.index_buffer.buffer
+    }
+    pub(crate) fn index_count(&self) -> usize {
+        self.index_count
+    }
+    /// Without DSA the vertex array and its index buffer have to be bound beforehand.
+    pub(crate) fn replace_indices(&mut self, indices: &[u32], usage: gl::types::GLenum) -> Result<(), GlError> {
+        self.index_buffer.replace(indices, usage)?;
+        self.index_count = indices.len();
+
+        Ok(())
+    }
+    /// Without DSA the vertex array and its index buffer have to be bound beforehand.
+    /// Writing past the end grows the buffer and the index count.
+    pub(crate) fn write_indices(&mut self, first: usize, indices: &[u32], state_cache: &mut GlStateCache) -> Result<(), GlError> {
+        let offset = first * std::mem::size_of::<u32>();
+        // Without DSA binding the new buffer already attached it to the bound vertex array.
+        if self.index_buffer.write(offset, indices, state_cache)? && dsa() {
+            gl_check!(
+                gl::VertexArrayElementBuffer(self.id, self.index_buffer.buffer.id()),
+                "Failed to set element buffer!"
+            )?;
+        }
+        self.index_count = self.index_count.max(first + indices.len());
+
+        Ok(())
     }
-    pub(crate) fn bind(&self) -> Result<(), GlError> {
-        gl_check!(gl::BindVertexArray(self.id), "Failed o bind vertex array!")
+    /// Has to be called before the vertex array is dropped, deleting it unbinds it and its buffers.
+    pub(crate) fn forget(&self, state_cache: &mut GlStateCache) {
+        state_cache.forget_vertex_array(self.id);
+        for stream in self.streams.iter().flatten() {
+            state_cache.forget_buffer(stream.buffer.buffer.id());
+        }
+        state_cache.forget_buffer(self.index_buffer.buffer.id());
     }
-    pub(crate) fn unbind(&self) -> Result<(), GlError> {
-        gl_check!(gl::BindVertexArray(0), "Failed to unbind vertex array!")
+    fn get_stream(&self, stream: u32) -> Result<&GlVertexStream, GlError> {
+        self.streams.get(stream as usize)
+            .and_then(|stream| stream.as_ref())
+            .ok_or_else(|| GlError::Error("Missing vertex stream".to_string(), format!("Stream {} wasn't created!", stream)))
     }
-    pub(crate) fn unbind_buffers(&self) -> Result<(), GlError> {
-        self.vertex_buffer.unbind()?;
-        self.index_buffer.unbind()
+    fn get_stream_mut(&mut self, stream: u32) -> Result<&mut GlVertexStream, GlError> {
+        self.streams.get_mut(stream as usize)
+            .and_then(|stream| stream.as_mut())
+            .ok_or_else(|| GlError::Error("Missing vertex stream".to_string(), format!("Stream {} wasn't created!", stream)))
     }
-    pub(crate) fn set_attribute(
+    /// Without DSA `buffer` has to be bound to `GL_ARRAY_BUFFER` beforehand.
+    /// A `divisor` of 0 advances the attribute per vertex, otherwise per `divisor` instances.
+    fn set_attribute(
         &self,
-        attrib_pos: gl::types::GLuint,
-        components: gl::types::GLint,
+        buffer: gl::types::GLuint,
+        base_offset: usize,
+        binding: gl::types::GLuint,
+        attribute: &VertexAttribute,
         stride: usize,
-        offset: gl::types::GLint,
+        divisor: gl::types::GLuint,
     ) -> Result<(), GlError> 
     {
-        gl_check!(
-            gl::VertexAttribPointer(
-                attrib_pos, 
-                components,
-                gl::FLOAT, 
-                gl::FALSE, 
-                stride as gl::types::GLint, 
-                offset as *const _,
-            ),
-            "Failed to call glVertexAttribPointer!"
-        )?;
-        gl_check!(gl::EnableVertexAttribArray(attrib_pos), "Failed to enable vertex attrib array!")
+        let location = attribute.location;
+        let components = attribute.components;
+        let component = component_to_opengl(attribute.component);
+        if attribute.kind == AttributeKind::DOUBLE && !supported(GlFeature::DOUBLE_ATTRIBUTES) {
+            return Err(GlError::Error(
+                "Double attributes unsupported".to_string(),
+                format!("Location {} needs glVertexAttribLPointer (OpenGL 4.1)!", location)
+            ));
+        }
+
+        if dsa() {
+            gl_check!(
+                gl::VertexArrayVertexBuffer(self.id, binding, buffer, base_offset as gl::types::GLintptr, stride as gl::types::GLint),
+                "Failed to set vertex buffer!"
+            )?;
+            let offset = attribute.offset as gl::types::GLuint;
+            match attribute.kind {
+                AttributeKind::FLOAT | AttributeKind::NORMALIZED => gl_check!(
+                    gl::VertexArrayAttribFormat(
+                        self.id,
+                        location,
+                        components,
+                        component,
+                        (attribute.kind == AttributeKind::NORMALIZED) as gl::types::GLboolean,
+                        offset,
+                    ),
+                    "Failed to call glVertexArrayAttribFormat!"
+                )?,
+                AttributeKind::INTEGER => gl_check!(
+                    gl::VertexArrayAttribIFormat(self.id, location, components, component, offset),
+                    "Failed to call glVertexArrayAttribIFormat!"
+                )?,
+                AttributeKind::DOUBLE => gl_check!(
+                    gl::VertexArrayAttribLFormat(self.id, location, components, component, offset),
+                    "Failed to call glVertexArrayAttribLFormat!"
+                )?,
+            }
+            gl_check!(gl::VertexArrayAttribBinding(self.id, location, binding), "Failed to set attribute binding!")?;
+            gl_check!(gl::VertexArrayBindingDivisor(self.id, binding, divisor), "Failed to set binding divisor!")?;
+            
+            return gl_check!(gl::EnableVertexArrayAttrib(self.id, location), "Failed to enable vertex attrib array!");
+        }
+
+        let stride = stride as gl::types::GLint;
+        let offset = (base_offset + attribute.offset as usize) as *const _;
+        match attribute.kind {
+            AttributeKind::FLOAT | AttributeKind::NORMALIZED => gl_check!(
+                gl::VertexAttribPointer(
+                    location, 
+                    components,
+                    component, 
+                    (attribute.kind == AttributeKind::NORMALIZED) as gl::types::GLboolean, 
+                    stride, 
+                    offset,
+                ),
+                "Failed to call glVertexAttribPointer!"
+            )?,
+            AttributeKind::INTEGER => gl_check!(
+                gl::VertexAttribIPointer(location, components, component, stride, offset),
+                "Failed to call glVertexAttribIPointer!"
+            )?,
+            AttributeKind::DOUBLE => gl_check!(
+                gl::VertexAttribLPointer(location, components, component, stride, offset),
+                "Failed to call glVertexAttribLPointer!"
+            )?,
+        }
+        // Also for 0, the location may have been instanced in an earlier layout.
+        gl_check!(gl::VertexAttribDivisor(location, divisor), "Failed to set VertexAttribDivisor!")?;
+        gl_check!(gl::EnableVertexAttribArray(location), "Failed to enable vertex attrib array!")
     }
 }
 impl Drop for GlVertexArray {
     fn drop(&mut self) {
-        self.unbind_buffers().unwrap();
-        self.unbind().unwrap();
         gl_check!(gl::DeleteVertexArrays(1, [self.id].as_ptr()), "Failed to delete vertex array!").unwrap();
     }
+}
+
+/// Without DSA the vertex array has to be bound beforehand.
+fn set_location_enabled(id: gl::types::GLuint, location: u32, enabled: bool) -> Result<(), GlError> {
+    match (dsa(), enabled) {
+        (true, true) => gl_check!(gl::EnableVertexArrayAttrib(id, location), "Failed to enable vertex attrib array!"),
+        (true, false) => gl_check!(gl::DisableVertexArrayAttrib(id, location), "Failed to disable vertex attrib array!"),
+        (false, true) => gl_check!(gl::EnableVertexAttribArray(location), "Failed to enable vertex attrib array!"),
+        (false, false) => gl_check!(gl::DisableVertexAttribArray(location), "Failed to disable vertex attrib array!"),
+    }
+}
+/// Locations `old` feeds that `new` doesn't.
+fn stale_locations(old: &VertexLayout, new: &VertexLayout) -> Vec<u32> {
+    old.attributes
+        .iter()
+        .map(|attribute| attribute.location)
+        .filter(|location| new.attributes.iter().all(|attribute| attribute.location != *location))
+        .collect()
+}
+fn component_to_opengl(component: VertexComponent) -> gl::types::GLenum {
+    match component {
+        VertexComponent::HALF => gl::HALF_FLOAT,
+        VertexComponent::FLOAT => gl::FLOAT,
+        VertexComponent::DOUBLE => gl::DOUBLE,
+        VertexComponent::BYTE => gl::BYTE,
+        VertexComponent::UNSIGNED_BYTE => gl::UNSIGNED_BYTE,
+        VertexComponent::SHORT => gl::SHORT,
+        VertexComponent::UNSIGNED_SHORT => gl::UNSIGNED_SHORT,
+        VertexComponent::INT => gl::INT,
+        VertexComponent::UNSIGNED_INT => gl::UNSIGNED_INT,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer_core::lg_vertex::push_attribute;
+
+    use super::*;
+
+    fn layout(attributes: &[(u32, u32)]) -> VertexLayout {
+        let mut layout = VertexLayout { stride: 0, attributes: Vec::new() };
+        for &(location, columns) in attributes {
+            match columns {
+                4 => push_attribute::<[[f32; 4]; 4]>(&mut layout.attributes, location, 0, false),
+                _ => push_attribute::<[f32; 4]>(&mut layout.attributes, location, 0, false),
+            };
+        }
+        layout
+    }
+
+    #[test]
+    fn replaced_layouts_drop_unused_locations() {
+        let old = layout(&[(0, 1), (1, 4)]);
+        assert_eq!(stale_locations(&old, &layout(&[(0, 1), (1, 4)])), []);
+        assert_eq!(stale_locations(&old, &layout(&[(0, 1), (1, 1)])), [2, 3, 4]);
+        assert_eq!(stale_locations(&old, &layout(&[(3, 1)])), [0, 1, 2, 4]);
+        assert_eq!(stale_locations(&layout(&[]), &old), []);
+    }
 }
\ No newline at end of file
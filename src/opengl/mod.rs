@@ -7,6 +7,14 @@ pub(crate) mod gl_vertex_array;
 pub(crate) mod gl_program;
 pub(crate) mod gl_renderer;
 pub(crate) mod gl_storage;
+pub(crate) mod gl_pipeline;
+pub(crate) mod gl_state;
+pub(crate) mod gl_capabilities;
+pub(crate) mod gl_sampler;
+pub(crate) mod gl_bindless;
+pub(crate) mod gl_ring_buffer;
+pub(crate) mod gl_uniform_arena;
+pub(crate) mod gl_readback;
 
 #[derive(Clone, Debug)]
 pub enum GlError {
@@ -25,4 +33,12 @@ pub struct GlSpecs {
     pub gl_surface: glutin::surface::Surface<glutin::surface::WindowSurface>,
     pub gl_display: glutin::display::Display, 
     pub gl_context: glutin::context::PossiblyCurrentContext,
+    pub api: crate::renderer_core::GlApi,
+    /// The version the context was actually created with.
+    pub version: (u8, u8),
+    pub debug: bool,
+    /// Whether the default framebuffer does sRGB encoding on write.
+    pub srgb: bool,
+    /// Bytes per frame of the ring buffer.
+    pub stream_buffer_size: usize,
 }
\ No newline at end of file
@@ -0,0 +1,427 @@
+use std::{collections::HashMap, hash::Hash};
+
+use crate::StdError;
+
+use super::lg_texture::{bytes_per_pixel, LgTexture, MipMode, TextureDimension, TextureFormat, TextureRegion, TextureType};
+
+#[derive(Debug, Clone, Copy)]
+pub struct AtlasDescriptor {
+    pub page_width: u32,
+    pub page_height: u32,
+    /// Empty texels between neighbouring entries.
+    pub padding: u32,
+    /// Texels the edges of every entry are repeated outwards, keeps linear filtering from bleeding in the padding.
+    pub extrude: u32,
+    /// Every source has to use this format and type.
+    pub format: TextureFormat,
+    pub ty: TextureType,
+}
+impl Default for AtlasDescriptor {
+    fn default() -> Self {
+        Self {
+            page_width: 2048,
+            page_height: 2048,
+            padding: 1,
+            extrude: 1,
+            format: TextureFormat::RGBA,
+            ty: TextureType::UNSIGNED_BYTE,
+        }
+    }
+}
+
+/// Where an entry ended up, `uv` is (u0, v0, u1, v1) with v growing with the rows of the source.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasRegion {
+    pub page: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub uv: [f32; 4],
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    cursor: u32,
+}
+
+/// Single page of an atlas, uploaded like any other texture.
+#[derive(Debug, Clone)]
+pub struct AtlasPage {
+    width: u32,
+    height: u32,
+    bytes: Vec<u8>,
+    format: TextureFormat,
+    ty: TextureType,
+    shelves: Vec<Shelf>,
+    /// Area written since the last upload.
+    dirty: Option<TextureRegion>,
+}
+impl AtlasPage {
+    fn new(descriptor: &AtlasDescriptor) -> Self {
+        let size = descriptor.page_width as usize
+            * descriptor.page_height as usize
+            * bytes_per_pixel(descriptor.format, descriptor.ty);
+
+        Self {
+            width: descriptor.page_width,
+            height: descriptor.page_height,
+            bytes: vec![0; size],
+            format: descriptor.format,
+            ty: descriptor.ty,
+            shelves: Vec::new(),
+            dirty: None,
+        }
+    }
+    /// Finds room for a `width`x`height` slot, shelves are picked by the least wasted height.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let best = self.shelves
+            .iter_mut()
+            .filter(|shelf| shelf.height >= height && self.width - shelf.cursor >= width)
+            .min_by_key(|shelf| shelf.height - height);
+        if let Some(shelf) = best {
+            let x = shelf.cursor;
+            shelf.cursor += width;
+            return Some((x, shelf.y));
+        }
+
+        let y = self.shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+        if width > self.width || height > self.height - y {
+            return None;
+        }
+        self.shelves.push(Shelf { y, height, cursor: width });
+
+        Some((0, y))
+    }
+    /// Copies `texture` to (x, y) and repeats its edges `extrude` texels outwards.
+    fn blit(&mut self, texture: &impl LgTexture, x: u32, y: u32, extrude: u32) {
+        let bpp = bytes_per_pixel(self.format, self.ty);
+        let (src_width, src_height) = (texture.width() as i64, texture.height() as i64);
+        let extrude = extrude as i64;
+        let src = texture.bytes();
+
+        for row in -extrude..src_height + extrude {
+            let src_row = row.clamp(0, src_height - 1);
+            let dst_row = (y as i64 + extrude + row) as usize;
+
+            for col in -extrude..src_width + extrude {
+                let src_col = col.clamp(0, src_width - 1);
+                let dst_col = (x as i64 + extrude + col) as usize;
+
+                let src_start = (src_row * src_width + src_col) as usize * bpp;
+                let dst_start = (dst_row * self.width as usize + dst_col) * bpp;
+                self.bytes[dst_start..dst_start + bpp].copy_from_slice(&src[src_start..src_start + bpp]);
+            }
+        }
+
+        let extruded = TextureRegion::new_2d(
+            x,
+            y,
+            texture.width() + 2 * extrude as u32,
+            texture.height() + 2 * extrude as u32
+        );
+        self.mark_dirty(extruded);
+    }
+    fn mark_dirty(&mut self, region: TextureRegion) {
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => {
+                let (x, y) = (dirty.x.min(region.x), dirty.y.min(region.y));
+                let right = (dirty.x + dirty.width).max(region.x + region.width);
+                let bottom = (dirty.y + dirty.height).max(region.y + region.height);
+                TextureRegion::new_2d(x, y, right - x, bottom - y)
+            },
+            None => region,
+        });
+    }
+    pub(crate) fn take_dirty(&mut self) -> Option<TextureRegion> {
+        self.dirty.take()
+    }
+    /// Tightly packed texels of `region`.
+    pub(crate) fn region_bytes(&self, region: &TextureRegion) -> Vec<u8> {
+        let bpp = bytes_per_pixel(self.format, self.ty);
+        let row_bytes = region.width as usize * bpp;
+
+        let mut bytes = Vec::with_capacity(row_bytes * region.height as usize);
+        for row in region.y..region.y + region.height {
+            let start = (row as usize * self.width as usize + region.x as usize) * bpp;
+            bytes.extend_from_slice(&self.bytes[start..start + row_bytes]);
+        }
+
+        bytes
+    }
+}
+impl LgTexture for AtlasPage {
+    fn width(&self) -> u32 {
+        self.width
+    }
+    fn height(&self) -> u32 {
+        self.height
+    }
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+    fn size(&self) -> u64 {
+        self.bytes.len() as u64
+    }
+    fn mip_level(&self) -> u32 {
+        1
+    }
+    fn texture_type(&self) -> TextureType {
+        self.ty
+    }
+    fn texture_format(&self) -> TextureFormat {
+        self.format
+    }
+    /// Mips would mix neighbouring entries.
+    fn mip_mode(&self) -> MipMode {
+        MipMode::NONE
+    }
+}
+
+/// Packs many small textures into pages, page `i` is stored under `page_keys[i]` once uploaded.
+/// Entries can be inserted at any time, `LgRenderer::upload_atlas` only sends what changed.
+#[derive(Debug, Clone)]
+pub struct TextureAtlas<K: Eq + PartialEq + Hash> {
+    descriptor: AtlasDescriptor,
+    page_keys: Vec<K>,
+    pages: Vec<AtlasPage>,
+    regions: HashMap<K, AtlasRegion>,
+}
+impl<K: Clone + Eq + PartialEq + Hash> TextureAtlas<K> {
+    /// The number of keys limits how many pages the atlas may grow to.
+    pub fn new(descriptor: AtlasDescriptor, page_keys: Vec<K>) -> Self {
+        Self {
+            descriptor,
+            page_keys,
+            pages: Vec::new(),
+            regions: HashMap::new(),
+        }
+    }
+    /// Inserting an existing key with the same size overwrites its texels in place.
+    pub fn insert<T: LgTexture>(&mut self, key: K, texture: &T) -> Result<AtlasRegion, StdError> {
+        self.validate(texture)?;
+        let extrude = self.descriptor.extrude;
+
+        if let Some(region) = self.regions.get(&key).copied() {
+            if region.width != texture.width() || region.height != texture.height() {
+                return Err("Atlas entry already exists with a different size! (TextureAtlas)".into());
+            }
+            self.pages[region.page as usize].blit(texture, region.x - extrude, region.y - extrude, extrude);
+            return Ok(region);
+        }
+
+        let slot_width = texture.width() + 2 * extrude + self.descriptor.padding;
+        let slot_height = texture.height() + 2 * extrude + self.descriptor.padding;
+        if slot_width > self.descriptor.page_width || slot_height > self.descriptor.page_height {
+            return Err(format!(
+                "Atlas entry needs {}x{} texels with padding, pages are {}x{}! (TextureAtlas)",
+                slot_width,
+                slot_height,
+                self.descriptor.page_width,
+                self.descriptor.page_height
+            ).into());
+        }
+
+        let mut placement = None;
+        for (index, page) in self.pages.iter_mut().enumerate() {
+            if let Some(position) = page.allocate(slot_width, slot_height) {
+                placement = Some((index, position));
+                break;
+            }
+        }
+        if placement.is_none() && self.pages.len() < self.page_keys.len() {
+            let mut page = AtlasPage::new(&self.descriptor);
+            if let Some(position) = page.allocate(slot_width, slot_height) {
+                placement = Some((self.pages.len(), position));
+                self.pages.push(page);
+            }
+        }
+        let (page, (x, y)) = placement.ok_or("Atlas is full! (TextureAtlas)")?;
+
+        self.pages[page].blit(texture, x, y, extrude);
+
+        let (page_width, page_height) = (self.descriptor.page_width as f32, self.descriptor.page_height as f32);
+        let (x, y) = (x + extrude, y + extrude);
+        let region = AtlasRegion {
+            page: page as u32,
+            x,
+            y,
+            width: texture.width(),
+            height: texture.height(),
+            uv: [
+                x as f32 / page_width,
+                y as f32 / page_height,
+                (x + texture.width()) as f32 / page_width,
+                (y + texture.height()) as f32 / page_height,
+            ],
+        };
+        self.regions.insert(key, region);
+
+        Ok(region)
+    }
+    /// Inserts tallest first, which packs shelves tighter than insertion order.
+    pub fn insert_all<T: LgTexture>(&mut self, textures: &[(K, &T)]) -> Result<(), StdError> {
+        let mut sorted: Vec<_> = textures.iter().collect();
+        sorted.sort_by_key(|(_, texture)| std::cmp::Reverse(texture.height()));
+
+        for (key, texture) in sorted {
+            self.insert(key.clone(), *texture)?;
+        }
+
+        Ok(())
+    }
+    pub fn get(&self, key: &K) -> Option<&AtlasRegion> {
+        self.regions.get(key)
+    }
+    pub fn regions(&self) -> &HashMap<K, AtlasRegion> {
+        &self.regions
+    }
+    pub fn pages(&self) -> &[AtlasPage] {
+        &self.pages
+    }
+    /// Key the page is stored under.
+    pub fn page_key(&self, page: u32) -> Option<&K> {
+        self.page_keys.get(page as usize)
+    }
+    pub(crate) fn dirty_pages(&mut self) -> impl Iterator<Item = (&K, &mut AtlasPage)> {
+        self.page_keys
+            .iter()
+            .zip(self.pages.iter_mut())
+            .filter(|(_, page)| page.dirty.is_some())
+    }
+
+    fn validate(&self, texture: &impl LgTexture) -> Result<(), StdError> {
+        if texture.dimension() != TextureDimension::TEXTURE_2D || texture.compression().is_some() {
+            return Err("Only uncompressed 2D textures can be packed! (TextureAtlas)".into());
+        }
+        if texture.texture_format() != self.descriptor.format || texture.texture_type() != self.descriptor.ty {
+            return Err(format!(
+                "Texture is {:?}/{:?}, the atlas is {:?}/{:?}! (TextureAtlas)",
+                texture.texture_format(),
+                texture.texture_type(),
+                self.descriptor.format,
+                self.descriptor.ty
+            ).into());
+        }
+        if texture.width() == 0 || texture.height() == 0 {
+            return Err("Trying to pack an empty texture! (TextureAtlas)".into());
+        }
+        let expected = texture.width() as usize
+            * texture.height() as usize
+            * bytes_per_pixel(texture.texture_format(), texture.texture_type());
+        if texture.bytes().len() < expected {
+            return Err(format!("Texture needs {} bytes, got {}! (TextureAtlas)", expected, texture.bytes().len()).into());
+        }
+
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Single channel texture filled with `value`.
+    struct Solid {
+        width: u32,
+        height: u32,
+        bytes: Vec<u8>,
+    }
+    impl Solid {
+        fn new(width: u32, height: u32, value: u8) -> Self {
+            Self { width, height, bytes: vec![value; (width * height) as usize] }
+        }
+    }
+    impl LgTexture for Solid {
+        fn width(&self) -> u32 { self.width }
+        fn height(&self) -> u32 { self.height }
+        fn bytes(&self) -> &[u8] { &self.bytes }
+        fn size(&self) -> u64 { self.bytes.len() as u64 }
+        fn mip_level(&self) -> u32 { 1 }
+        fn texture_type(&self) -> TextureType { TextureType::UNSIGNED_BYTE }
+        fn texture_format(&self) -> TextureFormat { TextureFormat::R }
+    }
+
+    fn atlas(pages: usize) -> TextureAtlas<u32> {
+        let descriptor = AtlasDescriptor {
+            page_width: 16,
+            page_height: 16,
+            padding: 0,
+            extrude: 0,
+            format: TextureFormat::R,
+            ty: TextureType::UNSIGNED_BYTE,
+        };
+        TextureAtlas::new(descriptor, (100..100 + pages as u32).collect())
+    }
+
+    #[test]
+    fn fills_a_page() {
+        let mut atlas = atlas(1);
+        for key in 0..16 {
+            let region = atlas.insert(key, &Solid::new(4, 4, key as u8)).unwrap();
+            assert_eq!(region.page, 0);
+            assert_eq!((region.x, region.y), (key % 4 * 4, key / 4 * 4));
+        }
+        assert_eq!(atlas.pages().len(), 1);
+        assert!(atlas.insert(16, &Solid::new(1, 1, 0)).is_err());
+
+        let region = atlas.get(&5).unwrap();
+        assert_eq!(region.uv, [0.25, 0.25, 0.5, 0.5]);
+        let page = &atlas.pages()[0];
+        assert!(page.region_bytes(&TextureRegion::new_2d(region.x, region.y, 4, 4)).iter().all(|&b| b == 5));
+    }
+
+    #[test]
+    fn grows_into_new_pages() {
+        let mut atlas = atlas(2);
+        assert_eq!(atlas.insert(0, &Solid::new(16, 10, 1)).unwrap().page, 0);
+        assert_eq!(atlas.insert(1, &Solid::new(16, 10, 2)).unwrap().page, 1);
+        // Still fits below the first entry.
+        assert_eq!(atlas.insert(2, &Solid::new(8, 6, 3)).unwrap().page, 0);
+        assert_eq!(atlas.page_key(1), Some(&101));
+
+        assert!(atlas.insert(3, &Solid::new(16, 10, 4)).is_err());
+        assert_eq!(atlas.pages().len(), 2);
+    }
+
+    #[test]
+    fn padding_and_extrude_surround_entries() {
+        let mut atlas = atlas(1);
+        atlas.descriptor.padding = 1;
+        atlas.descriptor.extrude = 1;
+
+        let first = atlas.insert(0, &Solid::new(2, 2, 7)).unwrap();
+        let second = atlas.insert(1, &Solid::new(2, 2, 9)).unwrap();
+        assert_eq!((first.x, first.y), (1, 1));
+        assert_eq!((second.x, second.y), (6, 1));
+
+        let page = &atlas.pages()[0];
+        let extruded = page.region_bytes(&TextureRegion::new_2d(0, 0, 4, 4));
+        assert!(extruded.iter().all(|&b| b == 7));
+    }
+
+    #[test]
+    fn rejects_oversized_entries_without_adding_pages() {
+        let mut atlas = atlas(2);
+        assert!(atlas.insert(0, &Solid::new(17, 1, 0)).is_err());
+        assert!(atlas.insert(0, &Solid::new(1, 17, 0)).is_err());
+        assert!(atlas.pages().is_empty());
+
+        atlas.descriptor.extrude = 1;
+        assert!(atlas.insert(0, &Solid::new(15, 15, 0)).is_err());
+        assert!(atlas.pages().is_empty());
+        assert!(atlas.insert(0, &Solid::new(14, 14, 0)).is_ok());
+    }
+
+    #[test]
+    fn reinserting_overwrites_in_place() {
+        let mut atlas = atlas(1);
+        let region = atlas.insert(0, &Solid::new(4, 4, 1)).unwrap();
+        assert_eq!(atlas.insert(0, &Solid::new(4, 4, 2)).unwrap(), region);
+        assert!(atlas.insert(0, &Solid::new(2, 2, 3)).is_err());
+
+        let page = &atlas.pages()[0];
+        assert!(page.region_bytes(&TextureRegion::new_2d(region.x, region.y, 4, 4)).iter().all(|&b| b == 2));
+    }
+}
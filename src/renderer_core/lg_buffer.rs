@@ -0,0 +1,18 @@
+/// Plain data that can be copied to and from GPU buffers byte for byte.
+pub use bytemuck::{Pod, Zeroable};
+
+/// How often buffer data is expected to change, only a hint for the driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BufferUsage {
+    /// Set once, drawn many times.
+    #[default]
+    STATIC,
+    /// Changed repeatedly, drawn many times.
+    DYNAMIC,
+    /// Set once, drawn a few times, e.g. every frame.
+    STREAM,
+}
+
+/// Pending GPU readback, see `LgRenderer::try_readback`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReadbackHandle(pub(crate) u64);
\ No newline at end of file
@@ -0,0 +1,575 @@
+use std::path::Path;
+
+use ddsfile::{Caps2, D3D10ResourceDimension, D3DFormat, Dds, DxgiFormat, MiscFlag};
+
+use crate::StdError;
+
+use super::lg_texture::{full_mip_count, image_size, mip_extent, CompressedFormat, LgTexture, MipMode, TextureDimension, TextureFormat, TextureType};
+
+/// (format, type, compression) a container format maps onto.
+type FormatMapping = (TextureFormat, TextureType, Option<CompressedFormat>);
+
+/// Texture loaded from a KTX2 or DDS container, including its mip chain, array layers and cube faces.
+/// `bytes` holds every level one after another, largest first.
+#[derive(Debug, Clone)]
+pub struct ContainerTexture {
+    width: u32,
+    height: u32,
+    depth: u32,
+    dimension: TextureDimension,
+    bytes: Vec<u8>,
+    format: TextureFormat,
+    ty: TextureType,
+    compression: Option<CompressedFormat>,
+    mip_levels: u32,
+    mip_mode: MipMode,
+}
+impl ContainerTexture {
+    /// The container is picked by the file extension, `.ktx2` or `.dds`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, StdError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+
+        match path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_ascii_lowercase()).as_deref() {
+            Some("ktx2") => Self::from_ktx2(&bytes),
+            Some("dds") => Self::from_dds(&bytes),
+            _ => Err(format!("Unknown texture container: {}! (ContainerTexture)", path.display()).into()),
+        }
+    }
+    /// Supercompressed (Basis, zstd, zlib) files aren't supported.
+    pub fn from_ktx2(bytes: &[u8]) -> Result<Self, StdError> {
+        let reader = ktx2::Reader::new(bytes)?;
+        let header = reader.header();
+
+        if let Some(scheme) = header.supercompression_scheme {
+            return Err(format!("Supercompressed KTX2 ({:?}) isn't supported! (ContainerTexture)", scheme).into());
+        }
+        let format = header.format.ok_or("KTX2 without a format isn't supported! (ContainerTexture)")?;
+        let (format, ty, compression) = ktx2_format(format)?;
+
+        let (dimension, depth) = match (header.face_count, header.layer_count, header.pixel_depth) {
+            (6, 0, _) => (TextureDimension::CUBE, 6),
+            (6, layers, _) => (TextureDimension::CUBE_ARRAY, cube_faces(layers)?),
+            (_, 0, depth) if depth > 1 => (TextureDimension::TEXTURE_3D, depth),
+            (_, 0, _) => (TextureDimension::TEXTURE_2D, 1),
+            (_, layers, _) => (TextureDimension::TEXTURE_2D_ARRAY, layers),
+        };
+        // A level count of 0 asks for the chain to be generated on load.
+        let mip_levels = header.level_count.max(1);
+        validate_levels(mip_levels, header.pixel_width, header.pixel_height, depth, dimension)?;
+        let mip_mode = match header.level_count {
+            0 => MipMode::GENERATE,
+            1 => MipMode::NONE,
+            _ => MipMode::PROVIDED,
+        };
+
+        // Levels already store layers, then faces, then slices, which is the order `LgTexture` expects.
+        let bytes = reader.levels().flatten().copied().collect();
+
+        Ok(Self {
+            width: header.pixel_width,
+            height: header.pixel_height.max(1),
+            depth,
+            dimension,
+            bytes,
+            format,
+            ty,
+            compression,
+            mip_levels,
+            mip_mode,
+        })
+    }
+    /// BGRA files (A8R8G8B8, X8R8G8B8 and their DXGI equivalents) are swizzled to RGBA on load.
+    pub fn from_dds(bytes: &[u8]) -> Result<Self, StdError> {
+        let dds = Dds::read(bytes)?;
+
+        let bgra = bgra_layout(&dds);
+        let (format, ty, compression) = match (bgra, dds.get_dxgi_format(), dds.get_d3d_format()) {
+            (Some((format, _)), _, _) => (format, TextureType::UNSIGNED_BYTE, None),
+            (None, Some(format), _) => dxgi_format(format)?,
+            (None, None, Some(format)) => d3d_format(format)?,
+            (None, None, None) => return Err("DDS without a known format! (ContainerTexture)".into()),
+        };
+
+        let cube = dds.header.caps2.contains(Caps2::CUBEMAP)
+            || dds.header10.as_ref().is_some_and(|h| h.misc_flag.contains(MiscFlag::TEXTURECUBE));
+        let volume = dds.header.caps2.contains(Caps2::VOLUME)
+            || dds.header10.as_ref().is_some_and(|h| h.resource_dimension == D3D10ResourceDimension::Texture3D);
+        // For cube maps the DX10 array size counts cubes, not faces.
+        let layers = dds.header10.as_ref().map_or(1, |h| h.array_size.max(1));
+
+        let (dimension, depth) = match (volume, cube, layers) {
+            (true, _, _) => (TextureDimension::TEXTURE_3D, dds.get_depth().max(1)),
+            (false, true, 1) => (TextureDimension::CUBE, 6),
+            (false, true, layers) => (TextureDimension::CUBE_ARRAY, cube_faces(layers)?),
+            (false, false, 1) => (TextureDimension::TEXTURE_2D, 1),
+            (false, false, layers) => (TextureDimension::TEXTURE_2D_ARRAY, layers),
+        };
+        let (width, height) = (dds.get_width(), dds.get_height().max(1));
+        let mip_levels = dds.get_num_mipmap_levels().max(1);
+        validate_levels(mip_levels, width, height, depth, dimension)?;
+
+        // DDS stores each image with its whole mip chain, `LgTexture` wants every image of a level together.
+        let images = if volume { 1 } else { depth };
+        let level_sizes: Vec<usize> = (0..mip_levels)
+            .map(|level| {
+                let (level_width, level_height, level_depth) = mip_extent(width, height, depth, dimension, level);
+                let image_depth = if volume { level_depth } else { 1 };
+                image_size(level_width, level_height, image_depth, format, ty, compression)
+            })
+            .collect();
+        let image_stride = level_sizes.iter().try_fold(0usize, |stride, size| stride.checked_add(*size));
+        let needed = image_stride
+            .and_then(|stride| stride.checked_mul(images as usize))
+            .ok_or("DDS header describes more data than fits in memory! (ContainerTexture)")?;
+        let image_stride = image_stride.unwrap();
+
+        if dds.data.len() < needed {
+            return Err(format!(
+                "DDS data is too small, needs {} bytes, got {}! (ContainerTexture)",
+                needed,
+                dds.data.len()
+            ).into());
+        }
+
+        let mut bytes = Vec::with_capacity(needed);
+        let mut level_offset = 0;
+        for level_size in &level_sizes {
+            for image in 0..images as usize {
+                let start = image * image_stride + level_offset;
+                bytes.extend_from_slice(&dds.data[start..start + level_size]);
+            }
+            level_offset += level_size;
+        }
+        if let Some((_, opaque)) = bgra {
+            for pixel in bytes.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+                if opaque {
+                    pixel[3] = u8::MAX;
+                }
+            }
+        }
+
+        Ok(Self {
+            width,
+            height,
+            depth,
+            dimension,
+            bytes,
+            format,
+            ty,
+            compression,
+            mip_levels,
+            mip_mode: if mip_levels > 1 { MipMode::PROVIDED } else { MipMode::NONE },
+        })
+    }
+}
+impl LgTexture for ContainerTexture {
+    fn width(&self) -> u32 {
+        self.width
+    }
+    fn height(&self) -> u32 {
+        self.height
+    }
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+    fn size(&self) -> u64 {
+        self.bytes.len() as u64
+    }
+    fn mip_level(&self) -> u32 {
+        self.mip_levels
+    }
+    fn texture_type(&self) -> TextureType {
+        self.ty
+    }
+    fn texture_format(&self) -> TextureFormat {
+        self.format
+    }
+    fn compression(&self) -> Option<CompressedFormat> {
+        self.compression
+    }
+    fn mip_mode(&self) -> MipMode {
+        self.mip_mode
+    }
+    fn dimension(&self) -> TextureDimension {
+        self.dimension
+    }
+    fn depth(&self) -> u32 {
+        self.depth
+    }
+}
+
+/// Faces of a cube array with `layers` cubes.
+fn cube_faces(layers: u32) -> Result<u32, StdError> {
+    layers.checked_mul(6).ok_or_else(|| format!("Cube array with {} layers is too large! (ContainerTexture)", layers).into())
+}
+/// Headers can claim any level count, more than the full chain has no extent to go with it.
+fn validate_levels(levels: u32, width: u32, height: u32, depth: u32, dimension: TextureDimension) -> Result<(), StdError> {
+    let full_chain = full_mip_count(width, height, depth, dimension);
+    if levels > full_chain {
+        return Err(format!(
+            "{} mip levels, but a {}x{}x{} texture has at most {}! (ContainerTexture)",
+            levels, width, height, depth, full_chain
+        ).into());
+    }
+
+    Ok(())
+}
+fn compressed(format: CompressedFormat, srgb: bool) -> FormatMapping {
+    let tex_format = if srgb { TextureFormat::SRGB_ALPHA } else { TextureFormat::RGBA };
+    (tex_format, TextureType::UNSIGNED_BYTE, Some(format))
+}
+fn ktx2_format(format: ktx2::Format) -> Result<FormatMapping, StdError> {
+    use ktx2::Format as K;
+    use CompressedFormat as C;
+    use TextureFormat as F;
+    use TextureType as T;
+
+    Ok(match format {
+        K::R8_UNORM => (F::R, T::UNSIGNED_BYTE, None),
+        K::R8G8_UNORM => (F::RG, T::UNSIGNED_BYTE, None),
+        K::R8G8B8_UNORM => (F::RGB, T::UNSIGNED_BYTE, None),
+        K::R8G8B8_SRGB => (F::SRGB, T::UNSIGNED_BYTE, None),
+        K::R8G8B8A8_UNORM => (F::RGBA, T::UNSIGNED_BYTE, None),
+        K::R8G8B8A8_SRGB => (F::SRGB_ALPHA, T::UNSIGNED_BYTE, None),
+        K::R16_UNORM => (F::R, T::UNSIGNED_SHORT, None),
+        K::R16G16B16A16_UNORM => (F::RGBA, T::UNSIGNED_SHORT, None),
+        K::R16_SFLOAT => (F::R, T::HALF_FLOAT, None),
+        K::R16G16_SFLOAT => (F::RG, T::HALF_FLOAT, None),
+        K::R16G16B16A16_SFLOAT => (F::RGBA, T::HALF_FLOAT, None),
+        K::R32_SFLOAT => (F::R, T::FLOAT, None),
+        K::R32G32_SFLOAT => (F::RG, T::FLOAT, None),
+        K::R32G32B32A32_SFLOAT => (F::RGBA, T::FLOAT, None),
+        K::D16_UNORM => (F::DEPTH, T::UNSIGNED_SHORT, None),
+        K::D32_SFLOAT => (F::DEPTH, T::FLOAT, None),
+        K::BC1_RGB_UNORM_BLOCK => compressed(C::BC1, false),
+        K::BC1_RGB_SRGB_BLOCK => compressed(C::BC1, true),
+        K::BC1_RGBA_UNORM_BLOCK => compressed(C::BC1_ALPHA, false),
+        K::BC1_RGBA_SRGB_BLOCK => compressed(C::BC1_ALPHA, true),
+        K::BC2_UNORM_BLOCK => compressed(C::BC2, false),
+        K::BC2_SRGB_BLOCK => compressed(C::BC2, true),
+        K::BC3_UNORM_BLOCK => compressed(C::BC3, false),
+        K::BC3_SRGB_BLOCK => compressed(C::BC3, true),
+        K::BC4_UNORM_BLOCK => compressed(C::BC4, false),
+        K::BC4_SNORM_BLOCK => compressed(C::BC4_SIGNED, false),
+        K::BC5_UNORM_BLOCK => compressed(C::BC5, false),
+        K::BC5_SNORM_BLOCK => compressed(C::BC5_SIGNED, false),
+        K::BC6H_UFLOAT_BLOCK => compressed(C::BC6H, false),
+        K::BC6H_SFLOAT_BLOCK => compressed(C::BC6H_SIGNED, false),
+        K::BC7_UNORM_BLOCK => compressed(C::BC7, false),
+        K::BC7_SRGB_BLOCK => compressed(C::BC7, true),
+        K::ETC2_R8G8B8_UNORM_BLOCK => compressed(C::ETC2_RGB, false),
+        K::ETC2_R8G8B8_SRGB_BLOCK => compressed(C::ETC2_RGB, true),
+        K::ETC2_R8G8B8A1_UNORM_BLOCK => compressed(C::ETC2_RGB_A1, false),
+        K::ETC2_R8G8B8A1_SRGB_BLOCK => compressed(C::ETC2_RGB_A1, true),
+        K::ETC2_R8G8B8A8_UNORM_BLOCK => compressed(C::ETC2_RGBA, false),
+        K::ETC2_R8G8B8A8_SRGB_BLOCK => compressed(C::ETC2_RGBA, true),
+        K::EAC_R11_UNORM_BLOCK => compressed(C::EAC_R, false),
+        K::EAC_R11_SNORM_BLOCK => compressed(C::EAC_R_SIGNED, false),
+        K::EAC_R11G11_UNORM_BLOCK => compressed(C::EAC_RG, false),
+        K::EAC_R11G11_SNORM_BLOCK => compressed(C::EAC_RG_SIGNED, false),
+        K::ASTC_4x4_UNORM_BLOCK => compressed(C::ASTC_4x4, false),
+        K::ASTC_4x4_SRGB_BLOCK => compressed(C::ASTC_4x4, true),
+        K::ASTC_5x4_UNORM_BLOCK => compressed(C::ASTC_5x4, false),
+        K::ASTC_5x4_SRGB_BLOCK => compressed(C::ASTC_5x4, true),
+        K::ASTC_5x5_UNORM_BLOCK => compressed(C::ASTC_5x5, false),
+        K::ASTC_5x5_SRGB_BLOCK => compressed(C::ASTC_5x5, true),
+        K::ASTC_6x5_UNORM_BLOCK => compressed(C::ASTC_6x5, false),
+        K::ASTC_6x5_SRGB_BLOCK => compressed(C::ASTC_6x5, true),
+        K::ASTC_6x6_UNORM_BLOCK => compressed(C::ASTC_6x6, false),
+        K::ASTC_6x6_SRGB_BLOCK => compressed(C::ASTC_6x6, true),
+        K::ASTC_8x5_UNORM_BLOCK => compressed(C::ASTC_8x5, false),
+        K::ASTC_8x5_SRGB_BLOCK => compressed(C::ASTC_8x5, true),
+        K::ASTC_8x6_UNORM_BLOCK => compressed(C::ASTC_8x6, false),
+        K::ASTC_8x6_SRGB_BLOCK => compressed(C::ASTC_8x6, true),
+        K::ASTC_8x8_UNORM_BLOCK => compressed(C::ASTC_8x8, false),
+        K::ASTC_8x8_SRGB_BLOCK => compressed(C::ASTC_8x8, true),
+        K::ASTC_10x5_UNORM_BLOCK => compressed(C::ASTC_10x5, false),
+        K::ASTC_10x5_SRGB_BLOCK => compressed(C::ASTC_10x5, true),
+        K::ASTC_10x6_UNORM_BLOCK => compressed(C::ASTC_10x6, false),
+        K::ASTC_10x6_SRGB_BLOCK => compressed(C::ASTC_10x6, true),
+        K::ASTC_10x8_UNORM_BLOCK => compressed(C::ASTC_10x8, false),
+        K::ASTC_10x8_SRGB_BLOCK => compressed(C::ASTC_10x8, true),
+        K::ASTC_10x10_UNORM_BLOCK => compressed(C::ASTC_10x10, false),
+        K::ASTC_10x10_SRGB_BLOCK => compressed(C::ASTC_10x10, true),
+        K::ASTC_12x10_UNORM_BLOCK => compressed(C::ASTC_12x10, false),
+        K::ASTC_12x10_SRGB_BLOCK => compressed(C::ASTC_12x10, true),
+        K::ASTC_12x12_UNORM_BLOCK => compressed(C::ASTC_12x12, false),
+        K::ASTC_12x12_SRGB_BLOCK => compressed(C::ASTC_12x12, true),
+        _ => return Err(format!("Unsupported KTX2 format: {:?}! (ContainerTexture)", format).into()),
+    })
+}
+/// (format, whether the alpha channel is padding) for 8-bit BGRA layouts.
+fn bgra_layout(dds: &Dds) -> Option<(TextureFormat, bool)> {
+    match (dds.get_dxgi_format(), dds.get_d3d_format()) {
+        (Some(DxgiFormat::B8G8R8A8_Typeless | DxgiFormat::B8G8R8A8_UNorm), _) => Some((TextureFormat::RGBA, false)),
+        (Some(DxgiFormat::B8G8R8A8_UNorm_sRGB), _) => Some((TextureFormat::SRGB_ALPHA, false)),
+        (Some(DxgiFormat::B8G8R8X8_Typeless | DxgiFormat::B8G8R8X8_UNorm), _) => Some((TextureFormat::RGBA, true)),
+        (Some(DxgiFormat::B8G8R8X8_UNorm_sRGB), _) => Some((TextureFormat::SRGB_ALPHA, true)),
+        (None, Some(D3DFormat::A8R8G8B8)) => Some((TextureFormat::RGBA, false)),
+        (None, Some(D3DFormat::X8R8G8B8)) => Some((TextureFormat::RGBA, true)),
+        _ => None,
+    }
+}
+fn dxgi_format(format: DxgiFormat) -> Result<FormatMapping, StdError> {
+    use CompressedFormat as C;
+    use DxgiFormat as D;
+    use TextureFormat as F;
+    use TextureType as T;
+
+    Ok(match format {
+        D::R8_UNorm => (F::R, T::UNSIGNED_BYTE, None),
+        D::R8G8_UNorm => (F::RG, T::UNSIGNED_BYTE, None),
+        D::R8G8B8A8_UNorm => (F::RGBA, T::UNSIGNED_BYTE, None),
+        D::R8G8B8A8_UNorm_sRGB => (F::SRGB_ALPHA, T::UNSIGNED_BYTE, None),
+        D::R16_UNorm => (F::R, T::UNSIGNED_SHORT, None),
+        D::R16G16B16A16_UNorm => (F::RGBA, T::UNSIGNED_SHORT, None),
+        D::R16_Float => (F::R, T::HALF_FLOAT, None),
+        D::R16G16_Float => (F::RG, T::HALF_FLOAT, None),
+        D::R16G16B16A16_Float => (F::RGBA, T::HALF_FLOAT, None),
+        D::R32_Float => (F::R, T::FLOAT, None),
+        D::R32G32_Float => (F::RG, T::FLOAT, None),
+        D::R32G32B32A32_Float => (F::RGBA, T::FLOAT, None),
+        D::D16_UNorm => (F::DEPTH, T::UNSIGNED_SHORT, None),
+        D::D32_Float => (F::DEPTH, T::FLOAT, None),
+        // DXGI has no opaque BC1, the alpha variant decodes opaque blocks the same way.
+        D::BC1_Typeless | D::BC1_UNorm => compressed(C::BC1_ALPHA, false),
+        D::BC1_UNorm_sRGB => compressed(C::BC1_ALPHA, true),
+        D::BC2_Typeless | D::BC2_UNorm => compressed(C::BC2, false),
+        D::BC2_UNorm_sRGB => compressed(C::BC2, true),
+        D::BC3_Typeless | D::BC3_UNorm => compressed(C::BC3, false),
+        D::BC3_UNorm_sRGB => compressed(C::BC3, true),
+        D::BC4_Typeless | D::BC4_UNorm => compressed(C::BC4, false),
+        D::BC4_SNorm => compressed(C::BC4_SIGNED, false),
+        D::BC5_Typeless | D::BC5_UNorm => compressed(C::BC5, false),
+        D::BC5_SNorm => compressed(C::BC5_SIGNED, false),
+        D::BC6H_Typeless | D::BC6H_UF16 => compressed(C::BC6H, false),
+        D::BC6H_SF16 => compressed(C::BC6H_SIGNED, false),
+        D::BC7_Typeless | D::BC7_UNorm => compressed(C::BC7, false),
+        D::BC7_UNorm_sRGB => compressed(C::BC7, true),
+        _ => return Err(format!("Unsupported DDS format: {:?}! (ContainerTexture)", format).into()),
+    })
+}
+fn d3d_format(format: D3DFormat) -> Result<FormatMapping, StdError> {
+    use CompressedFormat as C;
+    use D3DFormat as D;
+    use TextureFormat as F;
+    use TextureType as T;
+
+    // D3D9 names list channels from the most significant bit, A8B8G8R8 is RGBA in memory.
+    Ok(match format {
+        D::L8 => (F::R, T::UNSIGNED_BYTE, None),
+        D::L16 => (F::R, T::UNSIGNED_SHORT, None),
+        D::A8B8G8R8 => (F::RGBA, T::UNSIGNED_BYTE, None),
+        D::A16B16G16R16 => (F::RGBA, T::UNSIGNED_SHORT, None),
+        D::R16F => (F::R, T::HALF_FLOAT, None),
+        D::G16R16F => (F::RG, T::HALF_FLOAT, None),
+        D::A16B16G16R16F => (F::RGBA, T::HALF_FLOAT, None),
+        D::R32F => (F::R, T::FLOAT, None),
+        D::G32R32F => (F::RG, T::FLOAT, None),
+        D::A32B32G32R32F => (F::RGBA, T::FLOAT, None),
+        D::DXT1 => compressed(C::BC1_ALPHA, false),
+        // DXT2/DXT4 are the premultiplied variants, the blocks are the same.
+        D::DXT2 | D::DXT3 => compressed(C::BC2, false),
+        D::DXT4 | D::DXT5 => compressed(C::BC3, false),
+        _ => return Err(format!("Unsupported DDS format: {:?}! (ContainerTexture)", format).into()),
+    })
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KTX2_MAGIC: [u8; 12] = [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+    const VK_R8G8B8A8_UNORM: u32 = 37;
+    const VK_R8G8B8A8_SNORM: u32 = 38;
+    const VK_BC1_RGB_UNORM_BLOCK: u32 = 131;
+    const KTX2_ZSTD: u32 = 2;
+
+    /// Builds a KTX2 file with a 2D image, `faces` faces and the given levels stored back to back.
+    fn ktx2(format: u32, width: u32, height: u32, faces: u32, level_count: u32, supercompression: u32, levels: &[&[u8]]) -> Vec<u8> {
+        let index_len = 24 * levels.len().max(1);
+        let mut file = KTX2_MAGIC.to_vec();
+        for field in [format, 1, width, height, 0, 0, faces, level_count, supercompression, 0, 0, 0, 0] {
+            file.extend_from_slice(&field.to_le_bytes());
+        }
+        file.extend_from_slice(&[0; 16]);
+
+        let mut offset = (80 + index_len) as u64;
+        for level in levels {
+            let len = level.len() as u64;
+            for field in [offset, len, len] {
+                file.extend_from_slice(&field.to_le_bytes());
+            }
+            offset += len;
+        }
+        for level in levels {
+            file.extend_from_slice(level);
+        }
+        file
+    }
+
+    const DDS_RGB: u32 = 0x40;
+    const DDS_ALPHA_PIXELS: u32 = 0x1;
+    const DDS_FOURCC: u32 = 0x4;
+    const DDS_CUBEMAP_ALL_FACES: u32 = 0x200 | 0xFC00;
+
+    /// Pixel format block: flags, fourcc, bit count and the R, G, B, A masks.
+    type DdsPixelFormat = [u32; 7];
+
+    const A8R8G8B8: DdsPixelFormat = [DDS_RGB | DDS_ALPHA_PIXELS, 0, 32, 0x00ff0000, 0x0000ff00, 0x000000ff, 0xff000000];
+    const X8R8G8B8: DdsPixelFormat = [DDS_RGB, 0, 32, 0x00ff0000, 0x0000ff00, 0x000000ff, 0];
+
+    fn fourcc(code: &[u8; 4]) -> DdsPixelFormat {
+        [DDS_FOURCC, u32::from_le_bytes(*code), 0, 0, 0, 0, 0]
+    }
+
+    fn dds(width: u32, height: u32, mip_count: u32, pixel_format: DdsPixelFormat, caps2: u32, dx10: Option<[u32; 5]>, data: &[u8]) -> Vec<u8> {
+        let mut file = b"DDS ".to_vec();
+        // CAPS | HEIGHT | WIDTH | PIXELFORMAT | MIPMAPCOUNT
+        let flags = 0x1 | 0x2 | 0x4 | 0x1000 | 0x20000;
+        for field in [124, flags, height, width, 0, 0, mip_count] {
+            file.extend_from_slice(&u32::to_le_bytes(field));
+        }
+        file.extend_from_slice(&[0; 44]);
+        file.extend_from_slice(&32u32.to_le_bytes());
+        for field in pixel_format {
+            file.extend_from_slice(&field.to_le_bytes());
+        }
+        for field in [0x1000, caps2, 0, 0, 0] {
+            file.extend_from_slice(&u32::to_le_bytes(field));
+        }
+        for field in dx10.into_iter().flatten() {
+            file.extend_from_slice(&field.to_le_bytes());
+        }
+        file.extend_from_slice(data);
+        file
+    }
+
+    #[test]
+    fn ktx2_rgba8_mip_chain() {
+        let level0: Vec<u8> = (0..16).collect();
+        let level1 = [100, 101, 102, 103];
+        let file = ktx2(VK_R8G8B8A8_UNORM, 2, 2, 1, 2, 0, &[&level0, &level1]);
+
+        let texture = ContainerTexture::from_ktx2(&file).unwrap();
+        assert_eq!((texture.width(), texture.height(), texture.depth()), (2, 2, 1));
+        assert_eq!(texture.dimension(), TextureDimension::TEXTURE_2D);
+        assert_eq!(texture.texture_format(), TextureFormat::RGBA);
+        assert_eq!(texture.texture_type(), TextureType::UNSIGNED_BYTE);
+        assert_eq!(texture.compression(), None);
+        assert_eq!(texture.mip_level(), 2);
+        assert_eq!(texture.mip_mode(), MipMode::PROVIDED);
+        assert_eq!(texture.bytes(), [level0.as_slice(), &level1].concat());
+    }
+
+    #[test]
+    fn ktx2_level_count() {
+        let level = [0; 16];
+        let single = ContainerTexture::from_ktx2(&ktx2(VK_R8G8B8A8_UNORM, 2, 2, 1, 1, 0, &[&level])).unwrap();
+        assert_eq!((single.mip_level(), single.mip_mode()), (1, MipMode::NONE));
+
+        let generated = ContainerTexture::from_ktx2(&ktx2(VK_R8G8B8A8_UNORM, 2, 2, 1, 0, 0, &[&level])).unwrap();
+        assert_eq!((generated.mip_level(), generated.mip_mode()), (1, MipMode::GENERATE));
+    }
+
+    #[test]
+    fn ktx2_compressed_cube() {
+        let faces = [7; 6 * 8];
+        let texture = ContainerTexture::from_ktx2(&ktx2(VK_BC1_RGB_UNORM_BLOCK, 4, 4, 6, 1, 0, &[&faces])).unwrap();
+        assert_eq!(texture.dimension(), TextureDimension::CUBE);
+        assert_eq!(texture.depth(), 6);
+        assert_eq!(texture.compression(), Some(CompressedFormat::BC1));
+        assert_eq!(texture.bytes().len(), 6 * 8);
+    }
+
+    #[test]
+    fn ktx2_rejects_bad_input() {
+        let level = [0; 16];
+        let file = ktx2(VK_R8G8B8A8_UNORM, 2, 2, 1, 1, 0, &[&level]);
+        assert!(ContainerTexture::from_ktx2(&file[..file.len() - 1]).is_err());
+        assert!(ContainerTexture::from_ktx2(&file[..40]).is_err());
+
+        let mut bad_magic = file.clone();
+        bad_magic[1] = b'X';
+        assert!(ContainerTexture::from_ktx2(&bad_magic).is_err());
+
+        assert!(ContainerTexture::from_ktx2(&ktx2(VK_R8G8B8A8_SNORM, 2, 2, 1, 1, 0, &[&level])).is_err());
+        assert!(ContainerTexture::from_ktx2(&ktx2(0, 2, 2, 1, 1, 0, &[&level])).is_err());
+        assert!(ContainerTexture::from_ktx2(&ktx2(VK_R8G8B8A8_UNORM, 2, 2, 1, 1, KTX2_ZSTD, &[&level])).is_err());
+    }
+
+    #[test]
+    fn ktx2_rejects_malformed_header() {
+        let texel = [0; 4];
+        let too_many_levels = ktx2(VK_R8G8B8A8_UNORM, 1, 1, 1, 40, 0, &[texel.as_slice(); 40]);
+        assert!(ContainerTexture::from_ktx2(&too_many_levels).is_err());
+
+        let mut huge_cube_array = ktx2(VK_R8G8B8A8_UNORM, 1, 1, 6, 1, 0, &[&[0; 6 * 4]]);
+        huge_cube_array[32..36].copy_from_slice(&0x3000_0000u32.to_le_bytes());
+        assert!(ContainerTexture::from_ktx2(&huge_cube_array).is_err());
+    }
+
+    #[test]
+    fn dds_bgra_is_swizzled() {
+        let texture = ContainerTexture::from_dds(&dds(2, 1, 1, A8R8G8B8, 0, None, &[1, 2, 3, 4, 5, 6, 7, 8])).unwrap();
+        assert_eq!(texture.texture_format(), TextureFormat::RGBA);
+        assert_eq!(texture.texture_type(), TextureType::UNSIGNED_BYTE);
+        assert_eq!(texture.bytes(), [3, 2, 1, 4, 7, 6, 5, 8]);
+
+        let opaque = ContainerTexture::from_dds(&dds(1, 1, 1, X8R8G8B8, 0, None, &[1, 2, 3, 0])).unwrap();
+        assert_eq!(opaque.bytes(), [3, 2, 1, 255]);
+
+        // DXGI_FORMAT_B8G8R8A8_UNORM_SRGB, 2D, one layer.
+        let srgb = dds(1, 1, 1, fourcc(b"DX10"), 0, Some([91, 3, 0, 1, 0]), &[1, 2, 3, 4]);
+        let srgb = ContainerTexture::from_dds(&srgb).unwrap();
+        assert_eq!(srgb.texture_format(), TextureFormat::SRGB_ALPHA);
+        assert_eq!(srgb.bytes(), [3, 2, 1, 4]);
+    }
+
+    #[test]
+    fn dds_dxt1() {
+        let texture = ContainerTexture::from_dds(&dds(8, 4, 1, fourcc(b"DXT1"), 0, None, &[0; 16])).unwrap();
+        assert_eq!(texture.compression(), Some(CompressedFormat::BC1_ALPHA));
+        assert_eq!(texture.mip_mode(), MipMode::NONE);
+        assert_eq!(texture.bytes().len(), 16);
+    }
+
+    #[test]
+    fn dds_cube_levels_are_grouped() {
+        // Each face stores a 2x2 and a 1x1 level, every byte tagged with its face and level.
+        let data: Vec<u8> = (0..6u8).flat_map(|face| [[face; 16].as_slice(), &[face + 10; 4]].concat()).collect();
+        let texture = ContainerTexture::from_dds(&dds(2, 2, 2, A8R8G8B8, DDS_CUBEMAP_ALL_FACES, None, &data)).unwrap();
+        assert_eq!(texture.dimension(), TextureDimension::CUBE);
+        assert_eq!(texture.mip_mode(), MipMode::PROVIDED);
+
+        let (level0, level1) = texture.bytes().split_at(6 * 16);
+        assert!(level0.chunks(16).enumerate().all(|(face, image)| image.iter().all(|&b| b == face as u8)));
+        assert!(level1.chunks(4).enumerate().all(|(face, image)| image.iter().all(|&b| b == face as u8 + 10)));
+    }
+
+    #[test]
+    fn dds_rejects_bad_input() {
+        assert!(ContainerTexture::from_dds(&dds(2, 2, 1, A8R8G8B8, 0, None, &[0; 15])).is_err());
+        let r5g6b5 = [DDS_RGB, 0, 16, 0xf800, 0x07e0, 0x001f, 0];
+        assert!(ContainerTexture::from_dds(&dds(2, 2, 1, r5g6b5, 0, None, &[0; 8])).is_err());
+
+        let mut bad_magic = dds(1, 1, 1, A8R8G8B8, 0, None, &[0; 4]);
+        bad_magic[0] = b'X';
+        assert!(ContainerTexture::from_dds(&bad_magic).is_err());
+        assert!(ContainerTexture::from_dds(b"DDS ").is_err());
+    }
+
+    #[test]
+    fn dds_rejects_malformed_header() {
+        assert!(ContainerTexture::from_dds(&dds(1, 1, 40, A8R8G8B8, 0, None, &[0; 4])).is_err());
+
+        // DXGI_FORMAT_R8G8B8A8_UNORM, 2D, TEXTURECUBE, more cubes than faces fit in a u32.
+        let huge_cube_array = dds(1, 1, 1, fourcc(b"DX10"), 0, Some([28, 3, 0x4, 0x3000_0000, 0]), &[0; 4]);
+        assert!(ContainerTexture::from_dds(&huge_cube_array).is_err());
+
+        // 65536x65536 RGBA8 is 16 GiB per layer, 2^31 layers overflow the total size.
+        let huge_array = dds(65536, 65536, 1, fourcc(b"DX10"), 0, Some([28, 3, 0, 0x8000_0000, 0]), &[0; 4]);
+        assert!(ContainerTexture::from_dds(&huge_array).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use std::path::Path;
+
+use image::{ColorType, DynamicImage};
+pub use image::RgbaImage;
+
+use crate::StdError;
+
+use super::lg_texture::{full_mip_count, LgTexture, MipMode, TextureDimension, TextureFormat, TextureType};
+
+#[derive(Debug, Clone, Copy)]
+pub struct ImageLoadOptions {
+    /// Images are stored top row first, GL expects the bottom row first.
+    pub flip_vertically: bool,
+    /// Multiplies color by alpha, done on the stored values so 8-bit sRGB images are premultiplied in sRGB space.
+    pub premultiply_alpha: bool,
+    /// Upload 8-bit images as `SRGB_ALPHA` instead of `RGBA`, float images are always linear.
+    pub srgb: bool,
+    pub mip_mode: MipMode,
+    /// Levels for `MipMode::GENERATE_LEVELS`, `None` is the full chain.
+    pub mip_levels: Option<u32>,
+}
+impl Default for ImageLoadOptions {
+    fn default() -> Self {
+        Self {
+            flip_vertically: true,
+            premultiply_alpha: false,
+            srgb: true,
+            mip_mode: MipMode::GENERATE,
+            mip_levels: None,
+        }
+    }
+}
+
+/// Decoded PNG/JPEG (8-bit RGBA) or Radiance HDR/OpenEXR (32-bit float RGBA) image.
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    width: u32,
+    height: u32,
+    bytes: Vec<u8>,
+    format: TextureFormat,
+    ty: TextureType,
+    mip_mode: MipMode,
+    mip_levels: u32,
+}
+impl ImageTexture {
+    pub fn from_file(path: impl AsRef<Path>, options: &ImageLoadOptions) -> Result<Self, StdError> {
+        let image = image::io::Reader::open(path)?
+            .with_guessed_format()?
+            .decode()?;
+
+        Ok(Self::from_image(image, options))
+    }
+    /// The format is guessed from the contents.
+    pub fn from_memory(bytes: &[u8], options: &ImageLoadOptions) -> Result<Self, StdError> {
+        let image = image::load_from_memory(bytes)?;
+
+        Ok(Self::from_image(image, options))
+    }
+    fn from_image(image: DynamicImage, options: &ImageLoadOptions) -> Self {
+        let image = if options.flip_vertically { image.flipv() } else { image };
+        let (width, height) = (image.width(), image.height());
+        let full_chain = full_mip_count(width, height, 1, TextureDimension::TEXTURE_2D);
+        let mip_levels = options.mip_levels.unwrap_or(full_chain).clamp(1, full_chain);
+
+        let is_float = matches!(image.color(), ColorType::Rgb32F | ColorType::Rgba32F);
+        let (bytes, format, ty) = if is_float {
+            let mut pixels = image.into_rgba32f().into_raw();
+            if options.premultiply_alpha {
+                for pixel in pixels.chunks_exact_mut(4) {
+                    let alpha = pixel[3];
+                    pixel[..3].iter_mut().for_each(|c| *c *= alpha);
+                }
+            }
+
+            let bytes = pixels.iter().flat_map(|c| c.to_ne_bytes()).collect();
+            (bytes, TextureFormat::RGBA, TextureType::FLOAT)
+        } else {
+            let mut pixels = image.into_rgba8().into_raw();
+            if options.premultiply_alpha {
+                for pixel in pixels.chunks_exact_mut(4) {
+                    let alpha = pixel[3] as u32;
+                    pixel[..3].iter_mut().for_each(|c| *c = ((*c as u32 * alpha + 127) / 255) as u8);
+                }
+            }
+
+            let format = if options.srgb { TextureFormat::SRGB_ALPHA } else { TextureFormat::RGBA };
+            (pixels, format, TextureType::UNSIGNED_BYTE)
+        };
+
+        Self {
+            width,
+            height,
+            bytes,
+            format,
+            ty,
+            mip_mode: options.mip_mode,
+            mip_levels,
+        }
+    }
+}
+impl LgTexture for ImageTexture {
+    fn width(&self) -> u32 {
+        self.width
+    }
+    fn height(&self) -> u32 {
+        self.height
+    }
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+    fn size(&self) -> u64 {
+        self.bytes.len() as u64
+    }
+    fn mip_level(&self) -> u32 {
+        self.mip_levels
+    }
+    fn texture_type(&self) -> TextureType {
+        self.ty
+    }
+    fn texture_format(&self) -> TextureFormat {
+        self.format
+    }
+    fn mip_mode(&self) -> MipMode {
+        self.mip_mode
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1x2 PNG, red on top of half transparent white.
+    fn png() -> Vec<u8> {
+        let image = image::RgbaImage::from_raw(1, 2, vec![255, 0, 0, 255, 255, 255, 255, 128]).unwrap();
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut bytes, image::ImageOutputFormat::Png).unwrap();
+
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn flips_to_bottom_row_first() {
+        let texture = ImageTexture::from_memory(&png(), &ImageLoadOptions::default()).unwrap();
+
+        assert_eq!((texture.width(), texture.height()), (1, 2));
+        assert_eq!(texture.bytes(), &[255, 255, 255, 128, 255, 0, 0, 255]);
+        assert_eq!(texture.texture_format(), TextureFormat::SRGB_ALPHA);
+        assert_eq!(texture.texture_type(), TextureType::UNSIGNED_BYTE);
+        assert_eq!(texture.mip_level(), 2);
+    }
+
+    #[test]
+    fn premultiplies_and_keeps_order() {
+        let options = ImageLoadOptions {
+            flip_vertically: false,
+            premultiply_alpha: true,
+            srgb: false,
+            ..Default::default()
+        };
+        let texture = ImageTexture::from_memory(&png(), &options).unwrap();
+
+        assert_eq!(texture.bytes(), &[255, 0, 0, 255, 128, 128, 128, 128]);
+        assert_eq!(texture.texture_format(), TextureFormat::RGBA);
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(ImageTexture::from_memory(&[0, 1, 2, 3], &ImageLoadOptions::default()).is_err());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,149 @@
+use super::{lg_shader::LgShader, lg_vertex::{GlVertex, VertexLayout}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimitiveTopology {
+    POINT_LIST,
+    LINE_LIST,
+    LINE_STRIP,
+    TRIANGLE_LIST,
+    TRIANGLE_STRIP,
+    TRIANGLE_FAN,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompareOp {
+    NEVER,
+    LESS,
+    EQUAL,
+    LESS_OR_EQUAL,
+    GREATER,
+    NOT_EQUAL,
+    GREATER_OR_EQUAL,
+    ALWAYS,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendFactor {
+    ZERO,
+    ONE,
+    SRC_COLOR,
+    ONE_MINUS_SRC_COLOR,
+    DST_COLOR,
+    ONE_MINUS_DST_COLOR,
+    SRC_ALPHA,
+    ONE_MINUS_SRC_ALPHA,
+    DST_ALPHA,
+    ONE_MINUS_DST_ALPHA,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendOp {
+    ADD,
+    SUBTRACT,
+    REVERSE_SUBTRACT,
+    MIN,
+    MAX,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullMode {
+    NONE,
+    FRONT,
+    BACK,
+    FRONT_AND_BACK,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontFace {
+    COUNTER_CLOCKWISE,
+    CLOCKWISE,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolygonMode {
+    FILL,
+    LINE,
+    POINT,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlendState {
+    pub enabled: bool,
+    pub src_color: BlendFactor,
+    pub dst_color: BlendFactor,
+    pub color_op: BlendOp,
+    pub src_alpha: BlendFactor,
+    pub dst_alpha: BlendFactor,
+    pub alpha_op: BlendOp,
+}
+impl Default for BlendState {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            src_color: BlendFactor::SRC_ALPHA,
+            dst_color: BlendFactor::ONE_MINUS_SRC_ALPHA,
+            color_op: BlendOp::ADD,
+            src_alpha: BlendFactor::SRC_ALPHA,
+            dst_alpha: BlendFactor::ONE_MINUS_SRC_ALPHA,
+            alpha_op: BlendOp::ADD,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthState {
+    pub test: bool,
+    pub write: bool,
+    pub compare: CompareOp,
+}
+impl Default for DepthState {
+    fn default() -> Self {
+        Self {
+            test: true,
+            write: true,
+            compare: CompareOp::LESS
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RasterState {
+    pub cull_mode: CullMode,
+    pub front_face: FrontFace,
+    pub polygon_mode: PolygonMode,
+}
+impl Default for RasterState {
+    fn default() -> Self {
+        Self {
+            cull_mode: CullMode::NONE,
+            front_face: FrontFace::COUNTER_CLOCKWISE,
+            polygon_mode: PolygonMode::FILL
+        }
+    }
+}
+
+/// Fixed-function state of a pipeline, the default matches what the renderer sets on init.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PipelineState {
+    pub blend: BlendState,
+    pub depth: DepthState,
+    pub raster: RasterState,
+}
+
+pub struct PipelineDescriptor<'a, K, S: LgShader> {
+    /// (program key, shaders), same as `LgRenderer::set_program`.
+    pub shaders: (K, &'a [(K, &'a S)]),
+    pub vertex_layout: VertexLayout,
+    pub topology: PrimitiveTopology,
+    pub state: PipelineState,
+}
+impl<'a, K, S: LgShader> PipelineDescriptor<'a, K, S> {
+    pub fn new<V: GlVertex>(shaders: (K, &'a [(K, &'a S)])) -> Self {
+        Self {
+            shaders,
+            vertex_layout: VertexLayout::of::<V>(),
+            topology: PrimitiveTopology::TRIANGLE_LIST,
+            state: PipelineState::default(),
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,103 @@
+use std::hash::Hash;
+
+use super::lg_pipeline::CompareOp;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WrapMode {
+    REPEAT,
+    MIRRORED_REPEAT,
+    CLAMP_TO_EDGE,
+    CLAMP_TO_BORDER,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterMode {
+    NEAREST,
+    LINEAR,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SamplerDescriptor {
+    /// (u, v, w)
+    pub wrap: (WrapMode, WrapMode, WrapMode),
+    pub min_filter: FilterMode,
+    pub mag_filter: FilterMode,
+    /// `None` samples only the base level.
+    pub mip_filter: Option<FilterMode>,
+    /// 1.0 disables anisotropic filtering, clamped to what the driver supports.
+    pub anisotropy: f32,
+    /// Ignored where the API has no sampler LOD bias (GLES).
+    pub lod_bias: f32,
+    pub min_lod: f32,
+    pub max_lod: f32,
+    /// Ignored without border clamping, `CLAMP_TO_BORDER` then falls back to `CLAMP_TO_EDGE`.
+    pub border_color: [f32; 4],
+    /// Depth comparison for shadow samplers.
+    pub compare: Option<CompareOp>,
+}
+impl SamplerDescriptor {
+    pub fn nearest() -> Self {
+        Self {
+            min_filter: FilterMode::NEAREST,
+            mag_filter: FilterMode::NEAREST,
+            mip_filter: Some(FilterMode::NEAREST),
+            ..Default::default()
+        }
+    }
+    pub fn clamped() -> Self {
+        Self {
+            wrap: (WrapMode::CLAMP_TO_EDGE, WrapMode::CLAMP_TO_EDGE, WrapMode::CLAMP_TO_EDGE),
+            ..Default::default()
+        }
+    }
+    fn bits(&self) -> [u32; 8] {
+        [
+            self.anisotropy.to_bits(),
+            self.lod_bias.to_bits(),
+            self.min_lod.to_bits(),
+            self.max_lod.to_bits(),
+            self.border_color[0].to_bits(),
+            self.border_color[1].to_bits(),
+            self.border_color[2].to_bits(),
+            self.border_color[3].to_bits(),
+        ]
+    }
+}
+impl Default for SamplerDescriptor {
+    fn default() -> Self {
+        Self {
+            wrap: (WrapMode::REPEAT, WrapMode::REPEAT, WrapMode::REPEAT),
+            min_filter: FilterMode::LINEAR,
+            mag_filter: FilterMode::LINEAR,
+            mip_filter: Some(FilterMode::LINEAR),
+            anisotropy: 1.0,
+            lod_bias: 0.0,
+            min_lod: -1000.0,
+            max_lod: 1000.0,
+            border_color: [0.0; 4],
+            compare: None,
+        }
+    }
+}
+// Compared bitwise so descriptors can key the sampler cache.
+impl PartialEq for SamplerDescriptor {
+    fn eq(&self, other: &Self) -> bool {
+        self.wrap == other.wrap
+            && self.min_filter == other.min_filter
+            && self.mag_filter == other.mag_filter
+            && self.mip_filter == other.mip_filter
+            && self.compare == other.compare
+            && self.bits() == other.bits()
+    }
+}
+impl Eq for SamplerDescriptor {}
+impl Hash for SamplerDescriptor {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.wrap.hash(state);
+        self.min_filter.hash(state);
+        self.mag_filter.hash(state);
+        self.mip_filter.hash(state);
+        self.compare.hash(state);
+        self.bits().hash(state);
+    }
+}
\ No newline at end of file
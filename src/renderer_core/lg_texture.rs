@@ -1,29 +1,283 @@
 use crate::StdError;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TextureType {
     UNSIGNED_BYTE,
+    BYTE,
+    UNSIGNED_SHORT,
+    SHORT,
+    UNSIGNED_INT,
+    INT,
+    HALF_FLOAT,
+    FLOAT,
+    /// Packed depth/stencil, only valid with `TextureFormat::DEPTH_STENCIL`.
+    UNSIGNED_INT_24_8,
 }
 impl TextureType {
     pub fn from(value: u32) -> Result<Self, StdError> {
         match value {
             0 => Ok(Self::UNSIGNED_BYTE),
-            _ => Err("Failed to convert from u32! (TextureFormat)".into())
+            1 => Ok(Self::BYTE),
+            2 => Ok(Self::UNSIGNED_SHORT),
+            3 => Ok(Self::SHORT),
+            4 => Ok(Self::UNSIGNED_INT),
+            5 => Ok(Self::INT),
+            6 => Ok(Self::HALF_FLOAT),
+            7 => Ok(Self::FLOAT),
+            8 => Ok(Self::UNSIGNED_INT_24_8),
+            _ => Err("Failed to convert from u32! (TextureType)".into())
+        }
+    }
+    /// Size of a single component in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::UNSIGNED_BYTE | Self::BYTE => 1,
+            Self::UNSIGNED_SHORT | Self::SHORT | Self::HALF_FLOAT => 2,
+            Self::UNSIGNED_INT | Self::INT | Self::FLOAT | Self::UNSIGNED_INT_24_8 => 4,
         }
     }
+    pub fn is_packed(&self) -> bool {
+        matches!(self, Self::UNSIGNED_INT_24_8)
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Layout of the uploaded pixels, the GPU side format is derived from this together with the `TextureType`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TextureFormat {
-    RGBA
+    RGBA,
+    R,
+    RG,
+    RGB,
+    /// sRGB encoded color, linear alpha.
+    SRGB_ALPHA,
+    SRGB,
+    /// Unnormalized integers, sampled with `usampler`/`isampler`.
+    R_INTEGER,
+    RG_INTEGER,
+    RGBA_INTEGER,
+    DEPTH,
+    DEPTH_STENCIL,
 }
 impl TextureFormat {
     pub fn from(value: u32) -> Result<Self, StdError> {
         match value {
             0 => Ok(Self::RGBA),
+            1 => Ok(Self::R),
+            2 => Ok(Self::RG),
+            3 => Ok(Self::RGB),
+            4 => Ok(Self::SRGB_ALPHA),
+            5 => Ok(Self::SRGB),
+            6 => Ok(Self::R_INTEGER),
+            7 => Ok(Self::RG_INTEGER),
+            8 => Ok(Self::RGBA_INTEGER),
+            9 => Ok(Self::DEPTH),
+            10 => Ok(Self::DEPTH_STENCIL),
             _ => Err("Failed to convert from u32! (TextureFormat)".into())
         }
     }
+    pub fn channels(&self) -> usize {
+        match self {
+            Self::R | Self::R_INTEGER | Self::DEPTH => 1,
+            Self::RG | Self::RG_INTEGER | Self::DEPTH_STENCIL => 2,
+            Self::RGB | Self::SRGB => 3,
+            Self::RGBA | Self::SRGB_ALPHA | Self::RGBA_INTEGER => 4,
+        }
+    }
+    pub fn is_integer(&self) -> bool {
+        matches!(self, Self::R_INTEGER | Self::RG_INTEGER | Self::RGBA_INTEGER)
+    }
+    pub fn is_depth(&self) -> bool {
+        matches!(self, Self::DEPTH | Self::DEPTH_STENCIL)
+    }
+    pub fn is_srgb(&self) -> bool {
+        matches!(self, Self::SRGB | Self::SRGB_ALPHA)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureDimension {
+    TEXTURE_2D,
+    TEXTURE_2D_ARRAY,
+    /// Faces in +X, -X, +Y, -Y, +Z, -Z order.
+    CUBE,
+    /// Layers of 6 faces each, see `CUBE`.
+    CUBE_ARRAY,
+    TEXTURE_3D,
+}
+impl TextureDimension {
+    pub fn from(value: u32) -> Result<Self, StdError> {
+        match value {
+            0 => Ok(Self::TEXTURE_2D),
+            1 => Ok(Self::TEXTURE_2D_ARRAY),
+            2 => Ok(Self::CUBE),
+            3 => Ok(Self::CUBE_ARRAY),
+            4 => Ok(Self::TEXTURE_3D),
+            _ => Err("Failed to convert from u32! (TextureDimension)".into())
+        }
+    }
+}
+
+/// Block-compressed GPU formats, sRGB variants are picked when `texture_format()` is sRGB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompressedFormat {
+    /// DXT1, opaque.
+    BC1,
+    /// DXT1 with 1-bit alpha.
+    BC1_ALPHA,
+    BC2,
+    BC3,
+    BC4,
+    BC4_SIGNED,
+    BC5,
+    BC5_SIGNED,
+    /// Unsigned HDR.
+    BC6H,
+    BC6H_SIGNED,
+    BC7,
+    ETC2_RGB,
+    /// ETC2 with 1-bit alpha.
+    ETC2_RGB_A1,
+    ETC2_RGBA,
+    EAC_R,
+    EAC_R_SIGNED,
+    EAC_RG,
+    EAC_RG_SIGNED,
+    ASTC_4x4,
+    ASTC_5x4,
+    ASTC_5x5,
+    ASTC_6x5,
+    ASTC_6x6,
+    ASTC_8x5,
+    ASTC_8x6,
+    ASTC_8x8,
+    ASTC_10x5,
+    ASTC_10x6,
+    ASTC_10x8,
+    ASTC_10x10,
+    ASTC_12x10,
+    ASTC_12x12,
+}
+impl CompressedFormat {
+    pub const ALL: [Self; 32] = [
+        Self::BC1, Self::BC1_ALPHA, Self::BC2, Self::BC3, Self::BC4, Self::BC4_SIGNED, Self::BC5, Self::BC5_SIGNED,
+        Self::BC6H, Self::BC6H_SIGNED, Self::BC7,
+        Self::ETC2_RGB, Self::ETC2_RGB_A1, Self::ETC2_RGBA, Self::EAC_R, Self::EAC_R_SIGNED, Self::EAC_RG, Self::EAC_RG_SIGNED,
+        Self::ASTC_4x4, Self::ASTC_5x4, Self::ASTC_5x5, Self::ASTC_6x5, Self::ASTC_6x6, Self::ASTC_8x5, Self::ASTC_8x6,
+        Self::ASTC_8x8, Self::ASTC_10x5, Self::ASTC_10x6, Self::ASTC_10x8, Self::ASTC_10x10, Self::ASTC_12x10, Self::ASTC_12x12,
+    ];
+
+    /// Texels covered by a single block, (width, height).
+    pub fn block_extent(&self) -> (u32, u32) {
+        match self {
+            Self::ASTC_4x4 => (4, 4),
+            Self::ASTC_5x4 => (5, 4),
+            Self::ASTC_5x5 => (5, 5),
+            Self::ASTC_6x5 => (6, 5),
+            Self::ASTC_6x6 => (6, 6),
+            Self::ASTC_8x5 => (8, 5),
+            Self::ASTC_8x6 => (8, 6),
+            Self::ASTC_8x8 => (8, 8),
+            Self::ASTC_10x5 => (10, 5),
+            Self::ASTC_10x6 => (10, 6),
+            Self::ASTC_10x8 => (10, 8),
+            Self::ASTC_10x10 => (10, 10),
+            Self::ASTC_12x10 => (12, 10),
+            Self::ASTC_12x12 => (12, 12),
+            _ => (4, 4),
+        }
+    }
+    /// Size of a single block in bytes.
+    pub fn block_size(&self) -> usize {
+        match self {
+            Self::BC1 | Self::BC1_ALPHA | Self::BC4 | Self::BC4_SIGNED
+            | Self::ETC2_RGB | Self::ETC2_RGB_A1 | Self::EAC_R | Self::EAC_R_SIGNED => 8,
+            _ => 16,
+        }
+    }
+    pub fn has_srgb(&self) -> bool {
+        !matches!(
+            self,
+            Self::BC4 | Self::BC4_SIGNED | Self::BC5 | Self::BC5_SIGNED | Self::BC6H | Self::BC6H_SIGNED
+                | Self::EAC_R | Self::EAC_R_SIGNED | Self::EAC_RG | Self::EAC_RG_SIGNED
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MipMode {
+    /// Full chain generated from the base level.
+    GENERATE,
+    /// `mip_level()` levels generated from the base level.
+    GENERATE_LEVELS,
+    /// `bytes` holds `mip_level()` levels one after another, largest first.
+    PROVIDED,
+    /// Base level only.
+    NONE,
+}
+
+/// Texel region of a texture, `z`/`depth` select layers, cube faces or 3D slices.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextureRegion {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+}
+impl TextureRegion {
+    pub fn new_2d(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self { x, y, z: 0, width, height, depth: 1 }
+    }
+}
+
+/// Number of levels in a full mip chain.
+pub fn full_mip_count(width: u32, height: u32, depth: u32, dimension: TextureDimension) -> u32 {
+    let largest = match dimension {
+        TextureDimension::TEXTURE_3D => width.max(height).max(depth),
+        _ => width.max(height),
+    };
+
+    32 - largest.max(1).leading_zeros()
+}
+/// (width, height, depth) of a mip level, layers and faces don't shrink.
+pub fn mip_extent(width: u32, height: u32, depth: u32, dimension: TextureDimension, level: u32) -> (u32, u32, u32) {
+    let depth = match dimension {
+        TextureDimension::TEXTURE_3D => (depth >> level).max(1),
+        _ => depth,
+    };
+
+    ((width >> level).max(1), (height >> level).max(1), depth)
+}
+
+/// Size in bytes of `depth` images of `width`x`height` texels, partial blocks are rounded up.
+pub fn image_size(
+    width: u32,
+    height: u32,
+    depth: u32,
+    format: TextureFormat,
+    tex_type: TextureType,
+    compression: Option<CompressedFormat>
+) -> usize
+{
+    match compression {
+        Some(compressed) => {
+            let (block_width, block_height) = compressed.block_extent();
+            width.div_ceil(block_width) as usize
+                * height.div_ceil(block_height) as usize
+                * depth as usize
+                * compressed.block_size()
+        },
+        None => width as usize * height as usize * depth as usize * bytes_per_pixel(format, tex_type),
+    }
+}
+
+pub fn bytes_per_pixel(format: TextureFormat, tex_type: TextureType) -> usize {
+    if tex_type.is_packed() {
+        tex_type.size()
+    } else {
+        format.channels() * tex_type.size()
+    }
 }
 
 pub trait LgTexture {
@@ -31,7 +285,100 @@ pub trait LgTexture {
     fn height(&self) -> u32;
     fn bytes(&self) -> &[u8];
     fn size(&self) -> u64;
+    /// Number of mip levels, see `MipMode`. With the default `mip_mode` 0 means the full chain.
     fn mip_level(&self) -> u32;
     fn texture_type(&self) -> TextureType;
     fn texture_format(&self) -> TextureFormat;
+    /// `bytes` holds compressed blocks, `texture_type()` is ignored and `texture_format()` only selects sRGB.
+    fn compression(&self) -> Option<CompressedFormat> {
+        None
+    }
+    /// Generated from the base level, the full chain for a `mip_level()` of 0 and that many levels otherwise.
+    fn mip_mode(&self) -> MipMode {
+        match self.mip_level() {
+            0 => MipMode::GENERATE,
+            _ => MipMode::GENERATE_LEVELS,
+        }
+    }
+    fn dimension(&self) -> TextureDimension {
+        TextureDimension::TEXTURE_2D
+    }
+    /// Layers for arrays, faces for cube maps (6 per cube) and depth for 3D textures.
+    /// `bytes` holds the images one after another.
+    fn depth(&self) -> u32 {
+        match self.dimension() {
+            TextureDimension::CUBE => 6,
+            _ => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_sizes() {
+        assert_eq!(bytes_per_pixel(TextureFormat::RGBA, TextureType::UNSIGNED_BYTE), 4);
+        assert_eq!(bytes_per_pixel(TextureFormat::RGB, TextureType::FLOAT), 12);
+        assert_eq!(bytes_per_pixel(TextureFormat::RG, TextureType::HALF_FLOAT), 4);
+        assert_eq!(bytes_per_pixel(TextureFormat::R_INTEGER, TextureType::UNSIGNED_SHORT), 2);
+        assert_eq!(bytes_per_pixel(TextureFormat::DEPTH_STENCIL, TextureType::UNSIGNED_INT_24_8), 4);
+    }
+
+    #[test]
+    fn uncompressed_image_size() {
+        assert_eq!(image_size(3, 5, 1, TextureFormat::RGB, TextureType::UNSIGNED_BYTE, None), 45);
+        assert_eq!(image_size(4, 4, 6, TextureFormat::RGBA, TextureType::FLOAT, None), 4 * 4 * 6 * 16);
+    }
+
+    #[test]
+    fn compressed_image_size_rounds_up_blocks() {
+        let bc1 = Some(CompressedFormat::BC1);
+        assert_eq!(image_size(4, 4, 1, TextureFormat::RGBA, TextureType::UNSIGNED_BYTE, bc1), 8);
+        assert_eq!(image_size(5, 5, 1, TextureFormat::RGBA, TextureType::UNSIGNED_BYTE, bc1), 4 * 8);
+        assert_eq!(image_size(1, 1, 1, TextureFormat::RGBA, TextureType::UNSIGNED_BYTE, bc1), 8);
+
+        let astc = Some(CompressedFormat::ASTC_10x8);
+        assert_eq!(image_size(20, 9, 2, TextureFormat::RGBA, TextureType::UNSIGNED_BYTE, astc), 2 * 2 * 2 * 16);
+    }
+
+    struct Plain(u32);
+    impl LgTexture for Plain {
+        fn width(&self) -> u32 { 1 }
+        fn height(&self) -> u32 { 1 }
+        fn bytes(&self) -> &[u8] { &[] }
+        fn size(&self) -> u64 { 0 }
+        fn mip_level(&self) -> u32 { self.0 }
+        fn texture_type(&self) -> TextureType { TextureType::UNSIGNED_BYTE }
+        fn texture_format(&self) -> TextureFormat { TextureFormat::RGBA }
+    }
+
+    #[test]
+    fn default_mip_mode_follows_mip_level() {
+        assert_eq!(Plain(0).mip_mode(), MipMode::GENERATE);
+        assert_eq!(Plain(1).mip_mode(), MipMode::GENERATE_LEVELS);
+        assert_eq!(Plain(4).mip_mode(), MipMode::GENERATE_LEVELS);
+    }
+
+    #[test]
+    fn mip_chain() {
+        assert_eq!(full_mip_count(1, 1, 1, TextureDimension::TEXTURE_2D), 1);
+        assert_eq!(full_mip_count(256, 64, 1, TextureDimension::TEXTURE_2D), 9);
+        // Array layers don't count, 3D depth does.
+        assert_eq!(full_mip_count(4, 4, 64, TextureDimension::TEXTURE_2D_ARRAY), 3);
+        assert_eq!(full_mip_count(4, 4, 64, TextureDimension::TEXTURE_3D), 7);
+
+        assert_eq!(mip_extent(256, 64, 6, TextureDimension::CUBE, 7), (2, 1, 6));
+        assert_eq!(mip_extent(8, 8, 8, TextureDimension::TEXTURE_3D, 2), (2, 2, 2));
+    }
+
+    #[test]
+    fn type_and_format_round_trip() {
+        for value in 0..9 {
+            assert!(TextureType::from(value).is_ok());
+        }
+        assert!(TextureType::from(9).is_err());
+        assert!(TextureFormat::from(u32::MAX).is_err());
+    }
 }
\ No newline at end of file
@@ -1,9 +1,64 @@
+use super::lg_texture::{TextureFormat, TextureType};
+
 #[derive(Clone, Copy, Debug)]
 pub enum LgUniformType {
     STRUCT,
     STORAGE_BUFFER,
+    /// Texture read or written as an `image*` uniform, bound with `LgRenderer::set_images`.
+    STORAGE_IMAGE,
+    /// Texture and sampler pair, bound with `LgRenderer::set_textures`.
     COMBINED_IMAGE_SAMPLER
 }
+
+/// Where a texture is bound, the explicit binding index (`layout(binding = N)`, the texture unit
+/// for samplers) or the uniform's name looked up in the current program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UniformBinding<'a> {
+    INDEX(u32),
+    NAME(&'a str),
+}
+impl<'a> UniformBinding<'a> {
+    /// Uses the declared binding index, the same way uniform buffers are bound.
+    pub fn of(uniform: &impl LgUniform) -> Self {
+        Self::INDEX(uniform.binding() as u32)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageAccess {
+    READ_ONLY,
+    WRITE_ONLY,
+    READ_WRITE,
+}
+impl ImageAccess {
+    pub fn writes(&self) -> bool {
+        !matches!(self, Self::READ_ONLY)
+    }
+}
+
+/// How a texture is bound as an image, the GLSL side declares the same binding and format.
+#[derive(Clone, Copy, Debug)]
+pub struct ImageBinding {
+    pub binding: u32,
+    pub access: ImageAccess,
+    pub level: u32,
+    /// `None` binds every layer, face or slice.
+    pub layer: Option<u32>,
+    /// Format the shader accesses the texels as, `None` uses the texture's own.
+    /// sRGB formats can't be used as images.
+    pub format: Option<(TextureFormat, TextureType)>,
+}
+impl ImageBinding {
+    pub fn new(binding: u32, access: ImageAccess) -> Self {
+        Self {
+            binding,
+            access,
+            level: 0,
+            layer: None,
+            format: None,
+        }
+    }
+}
 pub trait LgUniform {
     fn name(&self) -> &str;
     fn u_type(&self) -> LgUniformType;
@@ -13,4 +68,222 @@ pub trait LgUniform {
     fn get_raw_data(&self) -> *const std::ffi::c_void;
     fn set_data<D>(&mut self, data: &D);
     fn update_data(&self) -> bool;
-}
\ No newline at end of file
+}
+
+/// Derives `LgUniform` from `#[uniform(...)]` attributes and checks the std140/std430 layout at compile time.
+pub use lg_renderer_derive::LgUniform;
+/// Derives `GlslType` for structs nested in uniforms.
+pub use lg_renderer_derive::GlslType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlslLayoutRule {
+    STD140,
+    STD430,
+}
+
+/// Size and alignment of a type under a GLSL layout rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlslLayout {
+    pub size: usize,
+    pub align: usize,
+    /// Whether the Rust type has exactly this layout.
+    pub matches: bool,
+}
+impl GlslLayout {
+    pub const fn new(size: usize, align: usize) -> Self {
+        Self { size, align, matches: true }
+    }
+}
+
+/// Rust types with a GLSL counterpart.
+/// `[f32; 2..=4]` and its `i32`/`u32` versions are vectors, arrays of vectors double as matrices
+/// (`[[f32; 4]; 4]` is a `mat4`). Scalar arrays and `bool` have no Rust type with a matching layout.
+///
+/// # Safety
+/// The layouts have to be what GLSL uses for the type.
+pub unsafe trait GlslType {
+    const STD140: GlslLayout;
+    const STD430: GlslLayout;
+}
+/// Types that can be array elements, scalars can't be since `[f32; N]` is a vector.
+///
+/// # Safety
+/// Same as `GlslType`.
+pub unsafe trait GlslArrayElement: GlslType {}
+
+const fn round_up(value: usize, align: usize) -> usize {
+    value.div_ceil(align) * align
+}
+const fn max(a: usize, b: usize) -> usize {
+    if a > b { a } else { b }
+}
+
+macro_rules! glsl_scalar {
+    ($($ty:ty), *) => {$(
+        unsafe impl GlslType for $ty {
+            const STD140: GlslLayout = GlslLayout::new(4, 4);
+            const STD430: GlslLayout = GlslLayout::new(4, 4);
+        }
+        unsafe impl GlslType for [$ty; 2] {
+            const STD140: GlslLayout = GlslLayout::new(8, 8);
+            const STD430: GlslLayout = GlslLayout::new(8, 8);
+        }
+        unsafe impl GlslType for [$ty; 3] {
+            const STD140: GlslLayout = GlslLayout::new(12, 16);
+            const STD430: GlslLayout = GlslLayout::new(12, 16);
+        }
+        unsafe impl GlslType for [$ty; 4] {
+            const STD140: GlslLayout = GlslLayout::new(16, 16);
+            const STD430: GlslLayout = GlslLayout::new(16, 16);
+        }
+        unsafe impl GlslArrayElement for [$ty; 2] {}
+        unsafe impl GlslArrayElement for [$ty; 3] {}
+        unsafe impl GlslArrayElement for [$ty; 4] {}
+    )*};
+}
+glsl_scalar!(f32, i32, u32);
+
+unsafe impl<T: GlslArrayElement, const N: usize> GlslType for [T; N] {
+    // std140 rounds the element alignment up to a vec4.
+    const STD140: GlslLayout = array_layout(T::STD140, round_up(T::STD140.align, 16), std::mem::size_of::<T>(), N);
+    const STD430: GlslLayout = array_layout(T::STD430, T::STD430.align, std::mem::size_of::<T>(), N);
+}
+unsafe impl<T: GlslArrayElement, const N: usize> GlslArrayElement for [T; N] {}
+
+const fn array_layout(element: GlslLayout, align: usize, rust_stride: usize, len: usize) -> GlslLayout {
+    let stride = round_up(element.size, align);
+
+    GlslLayout {
+        size: stride * len,
+        align,
+        matches: element.matches && stride == rust_stride,
+    }
+}
+
+/// Walks the fields of a struct in declaration order, used by the derive macros.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy)]
+pub struct GlslStructLayout {
+    rule: GlslLayoutRule,
+    end: usize,
+    align: usize,
+    matches: bool,
+    /// Whether the last field sits at its GLSL offset.
+    pub offset_matches: bool,
+}
+impl GlslStructLayout {
+    pub const fn new(rule: GlslLayoutRule) -> Self {
+        Self {
+            rule,
+            end: 0,
+            align: 1,
+            matches: true,
+            offset_matches: true,
+        }
+    }
+    pub const fn field(self, field: GlslLayout, rust_offset: usize) -> Self {
+        let offset = round_up(self.end, field.align);
+
+        Self {
+            rule: self.rule,
+            end: offset + field.size,
+            align: max(self.align, field.align),
+            matches: self.matches && field.matches && offset == rust_offset,
+            offset_matches: offset == rust_offset,
+        }
+    }
+    pub const fn finish(self, rust_size: usize) -> GlslLayout {
+        let align = match self.rule {
+            GlslLayoutRule::STD140 => round_up(self.align, 16),
+            GlslLayoutRule::STD430 => self.align,
+        };
+        let size = round_up(self.end, align);
+
+        GlslLayout {
+            size,
+            align,
+            matches: self.matches && size == rust_size,
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Vec2 = [f32; 2];
+    type Vec3 = [f32; 3];
+    type Vec4 = [f32; 4];
+
+    /// Lays out `fields` as (GLSL layout, Rust offset), returns whether every offset matched and the struct layout.
+    fn layout(rule: GlslLayoutRule, fields: &[(GlslLayout, usize)], rust_size: usize) -> (bool, GlslLayout) {
+        let mut offsets_match = true;
+        let mut layout = GlslStructLayout::new(rule);
+        for (field, offset) in fields {
+            layout = layout.field(*field, *offset);
+            offsets_match &= layout.offset_matches;
+        }
+        (offsets_match, layout.finish(rust_size))
+    }
+
+    #[test]
+    fn vec3_packs_with_a_float() {
+        // vec3 then float share 16 bytes.
+        let fields = [(Vec3::STD140, 0), (f32::STD140, 12)];
+        assert_eq!(layout(GlslLayoutRule::STD140, &fields, 16), (true, GlslLayout::new(16, 16)));
+
+        // float then vec3 needs 12 bytes of padding in between.
+        let (offsets, _) = layout(GlslLayoutRule::STD140, &[(f32::STD140, 0), (Vec3::STD140, 4)], 16);
+        assert!(!offsets);
+        let (offsets, struct_layout) = layout(GlslLayoutRule::STD140, &[(f32::STD140, 0), (Vec3::STD140, 16)], 32);
+        assert!(offsets && struct_layout.matches);
+    }
+
+    #[test]
+    fn arrays() {
+        // std140 pads every element to a vec4, std430 doesn't.
+        assert_eq!(<[Vec2; 4]>::STD140, GlslLayout { size: 64, align: 16, matches: false });
+        assert_eq!(<[Vec2; 4]>::STD430, GlslLayout::new(32, 8));
+        assert_eq!(<[Vec4; 3]>::STD140, GlslLayout::new(48, 16));
+        // vec3 elements have a 16 byte stride, Rust packs them in 12.
+        assert_eq!(<[Vec3; 2]>::STD140, GlslLayout { size: 32, align: 16, matches: false });
+        assert_eq!(<[Vec3; 2]>::STD430, GlslLayout { size: 32, align: 16, matches: false });
+    }
+
+    #[test]
+    fn matrices() {
+        assert_eq!(<[Vec4; 4]>::STD140, GlslLayout::new(64, 16));
+        assert_eq!(<[Vec4; 4]>::STD430, GlslLayout::new(64, 16));
+        // mat2 columns are padded to a vec4 in std140 only.
+        assert_eq!(<[Vec2; 2]>::STD140, GlslLayout { size: 32, align: 16, matches: false });
+        assert_eq!(<[Vec2; 2]>::STD430, GlslLayout::new(16, 8));
+        // mat3 needs padded columns in both.
+        assert_eq!(<[Vec3; 3]>::STD140, GlslLayout { size: 48, align: 16, matches: false });
+        assert_eq!(<[Vec3; 3]>::STD430, GlslLayout { size: 48, align: 16, matches: false });
+    }
+
+    #[test]
+    fn nested_structs() {
+        // struct { vec2 } is rounded up to a vec4 in std140.
+        let (_, inner140) = layout(GlslLayoutRule::STD140, &[(Vec2::STD140, 0)], 8);
+        assert_eq!(inner140, GlslLayout { size: 16, align: 16, matches: false });
+        let (_, inner430) = layout(GlslLayoutRule::STD430, &[(Vec2::STD430, 0)], 8);
+        assert_eq!(inner430, GlslLayout::new(8, 8));
+
+        // float then the struct, which starts at its alignment.
+        let (offsets, outer) = layout(GlslLayoutRule::STD430, &[(f32::STD430, 0), (inner430, 8)], 16);
+        assert!(offsets);
+        assert_eq!(outer, GlslLayout::new(16, 8));
+        let (offsets, _) = layout(GlslLayoutRule::STD430, &[(f32::STD430, 0), (inner430, 4)], 12);
+        assert!(!offsets);
+    }
+
+    #[test]
+    fn trailing_padding() {
+        let fields = [(Vec4::STD140, 0), (f32::STD140, 16)];
+        assert_eq!(layout(GlslLayoutRule::STD140, &fields, 20).1, GlslLayout { size: 32, align: 16, matches: false });
+        assert!(layout(GlslLayoutRule::STD140, &fields, 32).1.matches);
+        // std430 doesn't round the struct up to a vec4.
+        let (_, scalars) = layout(GlslLayoutRule::STD430, &[(f32::STD430, 0), (f32::STD430, 4)], 8);
+        assert_eq!(scalars, GlslLayout::new(8, 4));
+    }
+}
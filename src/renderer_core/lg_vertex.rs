@@ -1,31 +1,347 @@
+use std::marker::PhantomData;
+
+pub use lg_renderer_derive::LgVertex;
+
+/// Fields can't share a location, matrices included:
+/// ```
+/// use lg_renderer::renderer_core::lg_vertex::LgVertex;
+///
+/// #[derive(LgVertex)]
+/// struct Instance {
+///     transform: [[f32; 4]; 4],
+///     #[location = 4]
+///     color: [f32; 4],
+/// }
+/// ```
+/// ```compile_fail
+/// use lg_renderer::renderer_core::lg_vertex::LgVertex;
+///
+/// #[derive(LgVertex)]
+/// struct Instance {
+///     transform: [[f32; 4]; 4],
+///     // The matrix already takes locations 0 to 3.
+///     #[location = 2]
+///     color: [f32; 4],
+/// }
+/// ```
 pub trait LgVertex: GlVertex {}
+/// Implemented by `#[derive(LgVertex)]` or `lg_vertex!`.
+///
+/// `gl_info` used to be an `unsafe fn` returning `(location, components, offset)` with every component read
+/// as a float. It's safe now and returns `VertexAttribute`s, which also carry the component type and how the
+/// shader reads it, manual implementations can fill them with `push_attribute`. `lg_vertex!` uses `VertexAttributeType`
+/// where a field implements it and still counts the `f32`s in any other field.
 pub trait GlVertex {
-    /// (location, components, offset)
-    unsafe fn gl_info() -> Vec<(u32, i32, i32)>;
+    fn gl_info() -> Vec<VertexAttribute>;
+}
+
+/// Type of a single component as it's stored in the vertex buffer.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VertexComponent {
+    HALF,
+    FLOAT,
+    DOUBLE,
+    BYTE,
+    UNSIGNED_BYTE,
+    SHORT,
+    UNSIGNED_SHORT,
+    INT,
+    UNSIGNED_INT,
+}
+impl VertexComponent {
+    pub const fn size(self) -> usize {
+        match self {
+            Self::BYTE | Self::UNSIGNED_BYTE => 1,
+            Self::HALF | Self::SHORT | Self::UNSIGNED_SHORT => 2,
+            Self::FLOAT | Self::INT | Self::UNSIGNED_INT => 4,
+            Self::DOUBLE => 8,
+        }
+    }
+    pub const fn is_integer(self) -> bool {
+        !matches!(self, Self::HALF | Self::FLOAT | Self::DOUBLE)
+    }
+}
+
+/// How the shader sees an attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttributeKind {
+    /// `float`/`vec` input, integer components are converted as is.
+    FLOAT,
+    /// `float`/`vec` input, integer components are mapped to [0, 1] or [-1, 1].
+    NORMALIZED,
+    /// `int`/`uint`/`ivec`/`uvec` input.
+    INTEGER,
+    /// `double`/`dvec`/`dmat` input.
+    DOUBLE,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VertexAttribute {
+    pub location: u32,
+    /// 1 to 4.
+    pub components: i32,
+    pub offset: i32,
+    pub component: VertexComponent,
+    pub kind: AttributeKind,
+}
+
+/// Rust types that can be a vertex attribute.
+/// Matrices are arrays of columns and take one location per column.
+///
+/// # Safety
+/// `size_of::<Self>()` has to be `COMPONENT.size() * COMPONENTS * LOCATIONS`.
+pub unsafe trait VertexAttributeType {
+    const COMPONENT: VertexComponent;
+    const COMPONENTS: i32;
+    const LOCATIONS: u32 = 1;
 }
 
+macro_rules! vertex_attribute_type {
+    ($($ty:ty => $component:ident), *) => {
+        $(
+            unsafe impl VertexAttributeType for $ty {
+                const COMPONENT: VertexComponent = VertexComponent::$component;
+                const COMPONENTS: i32 = 1;
+            }
+            unsafe impl VertexAttributeType for [$ty; 1] {
+                const COMPONENT: VertexComponent = VertexComponent::$component;
+                const COMPONENTS: i32 = 1;
+            }
+            unsafe impl VertexAttributeType for [$ty; 2] {
+                const COMPONENT: VertexComponent = VertexComponent::$component;
+                const COMPONENTS: i32 = 2;
+            }
+            unsafe impl VertexAttributeType for [$ty; 3] {
+                const COMPONENT: VertexComponent = VertexComponent::$component;
+                const COMPONENTS: i32 = 3;
+            }
+            unsafe impl VertexAttributeType for [$ty; 4] {
+                const COMPONENT: VertexComponent = VertexComponent::$component;
+                const COMPONENTS: i32 = 4;
+            }
+        )*
+    };
+}
+vertex_attribute_type!(
+    Half => HALF, f32 => FLOAT, f64 => DOUBLE, 
+    i8 => BYTE, u8 => UNSIGNED_BYTE, i16 => SHORT, u16 => UNSIGNED_SHORT, i32 => INT, u32 => UNSIGNED_INT
+);
+
+macro_rules! vertex_attribute_matrix {
+    ($($ty:ty), *) => {
+        $(
+            unsafe impl<const C: usize> VertexAttributeType for [[$ty; C]; 2] where [$ty; C]: VertexAttributeType {
+                const COMPONENT: VertexComponent = <[$ty; C]>::COMPONENT;
+                const COMPONENTS: i32 = <[$ty; C]>::COMPONENTS;
+                const LOCATIONS: u32 = 2;
+            }
+            unsafe impl<const C: usize> VertexAttributeType for [[$ty; C]; 3] where [$ty; C]: VertexAttributeType {
+                const COMPONENT: VertexComponent = <[$ty; C]>::COMPONENT;
+                const COMPONENTS: i32 = <[$ty; C]>::COMPONENTS;
+                const LOCATIONS: u32 = 3;
+            }
+            unsafe impl<const C: usize> VertexAttributeType for [[$ty; C]; 4] where [$ty; C]: VertexAttributeType {
+                const COMPONENT: VertexComponent = <[$ty; C]>::COMPONENT;
+                const COMPONENTS: i32 = <[$ty; C]>::COMPONENTS;
+                const LOCATIONS: u32 = 4;
+            }
+        )*
+    };
+}
+vertex_attribute_matrix!(f32, f64);
+
+/// Raw bits of an IEEE 754 half float, for `HALF` components.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Half(pub u16);
+impl Half {
+    /// Rounds towards zero, out of range values become infinity.
+    pub fn from_f32(value: f32) -> Self {
+        let bits = value.to_bits();
+        let sign = ((bits >> 16) & 0x8000) as u16;
+        let exponent = ((bits >> 23) & 0xff) as i32;
+        let mantissa = bits & 0x007f_ffff;
+
+        if exponent == 0xff {
+            let nan = if mantissa != 0 { 0x0200 } else { 0 };
+            return Self(sign | 0x7c00 | nan);
+        }
+        let exponent = exponent - 127 + 15;
+        if exponent >= 0x1f {
+            return Self(sign | 0x7c00);
+        }
+        if exponent <= 0 {
+            if exponent < -10 {
+                return Self(sign);
+            }
+            let mantissa = mantissa | 0x0080_0000;
+            return Self(sign | (mantissa >> (14 - exponent)) as u16);
+        }
+
+        Self(sign | ((exponent as u16) << 10) | (mantissa >> 13) as u16)
+    }
+    pub fn to_f32(self) -> f32 {
+        let sign = ((self.0 & 0x8000) as u32) << 16;
+        let exponent = ((self.0 >> 10) & 0x1f) as u32;
+        let mantissa = (self.0 & 0x03ff) as u32;
+
+        let bits = match exponent {
+            0 if mantissa == 0 => sign,
+            0 => {
+                let shift = mantissa.leading_zeros() - 21;
+                sign | ((113 - shift) << 23) | (((mantissa << shift) & 0x03ff) << 13)
+            },
+            0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+            _ => sign | ((exponent + 112) << 23) | (mantissa << 13),
+        };
+
+        f32::from_bits(bits)
+    }
+}
+
+/// Integer components default to `INTEGER`, `normalized` turns them into `NORMALIZED`.
+/// Pushes one attribute per location of `T` and returns the next free location.
+pub fn push_attribute<T: VertexAttributeType>(
+    attributes: &mut Vec<VertexAttribute>,
+    location: u32,
+    offset: usize,
+    normalized: bool,
+) -> u32 {
+    let kind = match T::COMPONENT {
+        VertexComponent::DOUBLE => AttributeKind::DOUBLE,
+        VertexComponent::HALF | VertexComponent::FLOAT => AttributeKind::FLOAT,
+        _ if normalized => AttributeKind::NORMALIZED,
+        _ => AttributeKind::INTEGER,
+    };
+    let column_size = T::COMPONENT.size() * T::COMPONENTS as usize;
+
+    for column in 0..T::LOCATIONS {
+        attributes.push(VertexAttribute {
+            location: location + column,
+            components: T::COMPONENTS,
+            offset: (offset + column as usize * column_size) as i32,
+            component: T::COMPONENT,
+            kind,
+        });
+    }
+
+    location + T::LOCATIONS
+}
+
+/// Lets `lg_vertex!` pick `push_attribute` for `VertexAttributeType` fields, `TypedAttribute` is found
+/// on `&AttributeProbe<T>` before `FloatAttribute` needs another reference.
+#[doc(hidden)]
+pub struct AttributeProbe<T>(PhantomData<T>);
+impl<T> AttributeProbe<T> {
+    pub fn new(_: *const T) -> Self {
+        Self(PhantomData)
+    }
+}
+#[doc(hidden)]
+pub trait TypedAttribute {
+    fn push(&self, attributes: &mut Vec<VertexAttribute>, location: u32, offset: usize) -> u32;
+}
+impl<T: VertexAttributeType> TypedAttribute for AttributeProbe<T> {
+    fn push(&self, attributes: &mut Vec<VertexAttribute>, location: u32, offset: usize) -> u32 {
+        push_attribute::<T>(attributes, location, offset, false)
+    }
+}
+/// Any other field is read as `f32`s at a single location, like `lg_vertex!` always did.
+#[doc(hidden)]
+pub trait FloatAttribute {
+    fn push(&self, attributes: &mut Vec<VertexAttribute>, location: u32, offset: usize) -> u32;
+}
+impl<T> FloatAttribute for &AttributeProbe<T> {
+    fn push(&self, attributes: &mut Vec<VertexAttribute>, location: u32, offset: usize) -> u32 {
+        attributes.push(VertexAttribute {
+            location,
+            components: (std::mem::size_of::<T>() / std::mem::size_of::<f32>()) as i32,
+            offset: offset as i32,
+            component: VertexComponent::FLOAT,
+            kind: AttributeKind::FLOAT,
+        });
+
+        location + 1
+    }
+}
+
+/// Streams a VAO can have, `draw_instanced` uses the one after the last.
+pub const MAX_VERTEX_STREAMS: u32 = 15;
+
+/// How often a vertex stream advances.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum VertexStep {
+    #[default]
+    VERTEX,
+    /// Advances every n instances, 0 is treated as 1.
+    INSTANCE(u32),
+}
+impl VertexStep {
+    pub(crate) fn divisor(self) -> u32 {
+        match self {
+            Self::VERTEX => 0,
+            Self::INSTANCE(divisor) => divisor.max(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexLayout {
+    pub stride: usize,
+    pub attributes: Vec<VertexAttribute>,
+}
+impl VertexLayout {
+    pub fn of<V: GlVertex>() -> Self {
+        Self {
+            stride: std::mem::size_of::<V>(),
+            attributes: V::gl_info(),
+        }
+    }
+    /// Highest location used, matrices included.
+    pub fn last_location(&self) -> Option<u32> {
+        self.attributes.iter().map(|attribute| attribute.location).max()
+    }
+}
+
+/// Fields are given consecutive locations in order, use `#[derive(LgVertex)]` for overrides and normalized attributes.
+/// Fields without a `VertexAttributeType` impl take one location and are read as `f32`s:
+/// ```
+/// use lg_renderer::lg_vertex;
+/// use lg_renderer::renderer_core::lg_vertex::{AttributeKind, GlVertex, VertexComponent};
+///
+/// #[repr(C)]
+/// struct Position([f32; 3]);
+///
+/// #[repr(C)]
+/// struct Vertex {
+///     position: Position,
+///     color: [u8; 4],
+/// }
+/// lg_vertex!(Vertex, position, color);
+///
+/// let attributes = Vertex::gl_info();
+/// assert_eq!((attributes[0].components, attributes[0].component), (3, VertexComponent::FLOAT));
+/// assert_eq!((attributes[1].location, attributes[1].component), (1, VertexComponent::UNSIGNED_BYTE));
+/// assert_eq!(attributes[1].kind, AttributeKind::INTEGER);
+/// ```
 #[macro_export]
 macro_rules! lg_vertex {
     ($struct_name:ident, $($fields:tt), *) => {
         impl lg_renderer::renderer_core::lg_vertex::GlVertex for $struct_name {
-            unsafe fn gl_info() -> Vec<(u32, i32, i32)> {
-                const fn size_of_raw<T>(_: *const T) -> usize {
-                    core::mem::size_of::<T>()
-                }
+            fn gl_info() -> Vec<lg_renderer::renderer_core::lg_vertex::VertexAttribute> {
+                #[allow(unused_imports)]
+                use lg_renderer::renderer_core::lg_vertex::{FloatAttribute as _, TypedAttribute as _};
+
                 let mut result = Vec::new();
                 let mut location = 0;
                 $(
                     let dummy = core::mem::MaybeUninit::<$struct_name>::uninit();
                     let dummy_ptr = dummy.as_ptr();
-                    let member_ptr = core::ptr::addr_of!((*dummy_ptr).$fields);
-                    let member_offset = member_ptr as i32 - dummy_ptr as i32;
+                    let member_ptr = unsafe { core::ptr::addr_of!((*dummy_ptr).$fields) };
                     
-                    result.push((
-                        location,
-                        (size_of_raw(member_ptr) / core::mem::size_of::<f32>()) as i32,
-                        member_offset
-                    ));
-                    location += 1;
+                    let probe = lg_renderer::renderer_core::lg_vertex::AttributeProbe::new(member_ptr);
+                    location = (&probe).push(&mut result, location, core::mem::offset_of!($struct_name, $fields));
                 )*
                 
                 result
@@ -33,4 +349,70 @@ macro_rules! lg_vertex {
         }
         impl lg_renderer::renderer_core::lg_vertex::LgVertex for $struct_name {}
     };
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_from_f32() {
+        assert_eq!(Half::from_f32(0.0), Half(0x0000));
+        assert_eq!(Half::from_f32(-0.0), Half(0x8000));
+        assert_eq!(Half::from_f32(1.0), Half(0x3c00));
+        assert_eq!(Half::from_f32(-2.0), Half(0xc000));
+        assert_eq!(Half::from_f32(0.5), Half(0x3800));
+        assert_eq!(Half::from_f32(65504.0), Half(0x7bff));
+        // Smallest subnormal, and values below it.
+        assert_eq!(Half::from_f32(2f32.powi(-24)), Half(0x0001));
+        assert_eq!(Half::from_f32(2f32.powi(-26)), Half(0x0000));
+        assert_eq!(Half::from_f32(-2f32.powi(-15)), Half(0x8200));
+    }
+
+    #[test]
+    fn half_rounds_towards_zero() {
+        // 1 + 2^-11 is between 1.0 and the next half.
+        assert_eq!(Half::from_f32(1.0 + 2f32.powi(-11)), Half(0x3c00));
+        assert_eq!(Half::from_f32(65519.0), Half(0x7bff));
+    }
+
+    #[test]
+    fn half_special_values() {
+        assert_eq!(Half::from_f32(f32::INFINITY), Half(0x7c00));
+        assert_eq!(Half::from_f32(f32::NEG_INFINITY), Half(0xfc00));
+        assert_eq!(Half::from_f32(1e6), Half(0x7c00));
+        assert!(Half::from_f32(f32::NAN).to_f32().is_nan());
+        assert_eq!(Half(0x7c00).to_f32(), f32::INFINITY);
+    }
+
+    #[test]
+    fn half_round_trips() {
+        for bits in 0..=u16::MAX {
+            let half = Half(bits);
+            let value = half.to_f32();
+            if value.is_nan() {
+                continue;
+            }
+            assert_eq!(Half::from_f32(value), half, "{:#06x} -> {}", bits, value);
+        }
+    }
+
+    #[test]
+    fn matrices_take_a_location_per_column() {
+        let mut attributes = Vec::new();
+        let next = push_attribute::<[[f32; 3]; 4]>(&mut attributes, 2, 8, false);
+        assert_eq!(next, 6);
+        assert_eq!(attributes.iter().map(|a| (a.location, a.offset)).collect::<Vec<_>>(), [(2, 8), (3, 20), (4, 32), (5, 44)]);
+        assert!(attributes.iter().all(|a| a.components == 3 && a.kind == AttributeKind::FLOAT));
+    }
+
+    #[test]
+    fn integer_kinds() {
+        let mut attributes = Vec::new();
+        push_attribute::<[u8; 4]>(&mut attributes, 0, 0, true);
+        push_attribute::<[u16; 2]>(&mut attributes, 1, 4, false);
+        push_attribute::<f64>(&mut attributes, 2, 8, true);
+        let kinds: Vec<_> = attributes.iter().map(|a| a.kind).collect();
+        assert_eq!(kinds, [AttributeKind::NORMALIZED, AttributeKind::INTEGER, AttributeKind::DOUBLE]);
+    }
+}
@@ -1,14 +1,22 @@
 #![allow(non_camel_case_types)]
 
-use std::hash::Hash;
+use std::{hash::Hash, ops::Range, path::Path};
 
 use crate::{opengl::{gl_init::init_opengl, gl_renderer::GlRenderer}, StdError};
-use self::{lg_shader::LgShader, lg_texture::LgTexture, lg_uniform::LgUniform, lg_vertex::GlVertex};
+use self::{lg_atlas::TextureAtlas, lg_buffer::{BufferUsage, Pod, ReadbackHandle}, lg_image::RgbaImage, lg_pipeline::PipelineDescriptor, lg_sampler::SamplerDescriptor, lg_shader::LgShader, lg_texture::{CompressedFormat, LgTexture, TextureRegion}, lg_uniform::{ImageBinding, LgUniform, UniformBinding}, lg_vertex::{GlVertex, VertexStep}};
 
 pub mod lg_vertex;
 pub mod lg_texture;
 pub mod lg_uniform;
 pub mod lg_shader;
+pub mod lg_pipeline;
+pub mod lg_sampler;
+pub mod lg_image;
+pub mod lg_container;
+pub mod lg_atlas;
+pub mod lg_buffer;
+
+pub use crate::opengl::gl_state::{GlStateCounters, StateCounter};
 
 pub(crate) trait GraphicsApi {
     fn init(&mut self) -> Result<(), StdError>;
@@ -20,15 +28,100 @@ pub enum RendererAPI<K: Eq + PartialEq + Hash> {
     VULKAN(()),
 }
 
-pub enum CreationApiInfo {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlApi {
     OPEN_GL,
+    GLES,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlProfile {
+    CORE,
+    COMPATIBILITY,
+}
+
+/// Context creation starts at `max_version` and falls back one version at a time down to `min_version`.
+/// Versions below GL 3.3 and GLES 3.0 are never tried. Compute and image load/store need GL 4.2/4.3 or GLES 3.1
+/// and return an error on older contexts.
+#[derive(Debug, Clone, Copy)]
+pub struct GlContextInfo {
+    pub api: GlApi,
+    pub max_version: (u8, u8),
+    pub min_version: (u8, u8),
+    /// Ignored for GLES.
+    pub profile: GlProfile,
+    pub robustness: bool,
+    pub debug: bool,
+    /// Bytes per frame for transient instance, uniform and vertex data, three frames are kept in flight.
+    /// Rounded up to a multiple of 256.
+    pub stream_buffer_size: usize,
+}
+impl GlContextInfo {
+    pub fn gles() -> Self {
+        Self {
+            api: GlApi::GLES,
+            max_version: (3, 2),
+            min_version: (3, 0),
+            ..Default::default()
+        }
+    }
+}
+impl Default for GlContextInfo {
+    fn default() -> Self {
+        Self {
+            api: GlApi::OPEN_GL,
+            max_version: (4, 6),
+            min_version: (3, 3),
+            profile: GlProfile::CORE,
+            robustness: false,
+            debug: cfg!(debug_assertions),
+            stream_buffer_size: 4 << 20,
+        }
+    }
+}
+
+pub enum CreationApiInfo {
+    OPEN_GL(GlContextInfo),
     VULKAN,
 }
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SurfaceAttributes {
+    pub depth_bits: u8,
+    pub stencil_bits: u8,
+    pub alpha_bits: u8,
+    pub samples: u8,
+    pub srgb: bool,
+    pub transparency: bool,
+}
+
+/// Configs that don't meet `required` are rejected, the rest are ranked by how close they are to `preferred`.
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceConfigRequest {
+    pub required: SurfaceAttributes,
+    pub preferred: SurfaceAttributes,
+}
+impl Default for SurfaceConfigRequest {
+    fn default() -> Self {
+        Self {
+            required: SurfaceAttributes::default(),
+            preferred: SurfaceAttributes {
+                depth_bits: 24,
+                stencil_bits: 8,
+                alpha_bits: 8,
+                samples: 4,
+                srgb: false,
+                transparency: false,
+            },
+        }
+    }
+}
+
 pub struct CreationWindowInfo<'a> {
     pub event_loop: Option<&'a winit::event_loop::EventLoop<()>>,
     pub title: String,
     pub width: u32,
     pub height: u32,
+    pub surface: SurfaceConfigRequest,
 }
 impl<'a> CreationWindowInfo<'a> {
     pub fn new(title: &str, width: u32, height: u32) -> Self {
@@ -36,7 +129,8 @@ impl<'a> CreationWindowInfo<'a> {
             event_loop: None, 
             title: title.to_string(), 
             width, 
-            height 
+            height,
+            surface: SurfaceConfigRequest::default(),
         }
     }
 }
@@ -52,8 +146,8 @@ pub struct LgRenderer<K: Clone +  Default + Eq + PartialEq + Hash> {
 impl<K: Clone + Default + Eq + PartialEq + Hash> LgRenderer<K> {
     pub fn new(info: LgRendererCreationInfo) -> Result<(winit::window::Window, Self), StdError> {
         match &info.renderer_api {
-            CreationApiInfo::OPEN_GL => {
-                let (window, gl_specs) = init_opengl(info.window_info)?;
+            CreationApiInfo::OPEN_GL(context_info) => {
+                let (window, gl_specs) = init_opengl(info.window_info, context_info)?;
                 
                 Ok((window, Self {
                     api: RendererAPI::OPEN_GL(GlRenderer::new(gl_specs)?)
@@ -90,6 +184,19 @@ impl<K: Clone + Default + Eq + PartialEq + Hash> LgRenderer<K> {
             RendererAPI::VULKAN(_) => todo!(),
         }       
     }
+    /// State changes sent to the driver and skipped as redundant since the last reset.
+    pub fn state_counters(&self) -> GlStateCounters {
+        match &self.api {
+            RendererAPI::OPEN_GL(gl) => *gl.state_counters(),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    pub fn reset_state_counters(&mut self) {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.reset_state_counters(),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
 
     pub fn begin(&self) -> Result<(), StdError> {
         Ok(match &self.api {
@@ -105,6 +212,28 @@ impl<K: Clone + Default + Eq + PartialEq + Hash> LgRenderer<K> {
         
         Ok(())
     }
+    /// Reads back what was drawn this frame to the window as RGBA8, top row first. Call it after drawing and before `end`.
+    /// Textures drawn into are read with `capture_texture`.
+    pub fn capture_frame(&mut self) -> Result<RgbaImage, StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.capture_frame(),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    /// Reads a mip level of a 2D RGBA8 texture, top row first like `capture_frame`.
+    /// Other formats go through `read_texture_async`, GLES can't read textures back.
+    pub fn capture_texture(&mut self, key: K, level: u32) -> Result<RgbaImage, StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.capture_texture(key, level),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    /// `capture_frame` saved as PNG, whatever the extension of `path`.
+    pub fn save_frame_png(&mut self, path: impl AsRef<Path>) -> Result<(), StdError> {
+        self.capture_frame()?.save_with_format(path, image::ImageFormat::Png)?;
+
+        Ok(())
+    }
     pub fn resize(&self, new_size: (u32, u32)) -> Result<(), StdError> {
         match &self.api {
             RendererAPI::OPEN_GL(gl) => gl.resize(new_size)?,
@@ -113,20 +242,94 @@ impl<K: Clone + Default + Eq + PartialEq + Hash> LgRenderer<K> {
         
         Ok(())
     }
-    pub unsafe fn read_uniform_buffer<T: Clone>(&self, key: K, index: usize) -> Result<T, StdError> {
-        match &self.api {
-            RendererAPI::OPEN_GL(gl) => gl.read_buffer::<T>(key),
+    /// Reads the `index`th `T` of a uniform or storage buffer.
+    ///
+    /// # Safety
+    /// The bytes at that position have to be a valid `T`, prefer `read_buffer_slice`.
+    pub unsafe fn read_uniform_buffer<T: Clone>(&mut self, key: K, index: usize) -> Result<T, StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.read_buffer::<T>(key, index),
             RendererAPI::VULKAN(_) => todo!(),
         }
     }
-    pub fn set_uniform_buffer_data(&self, key: K, data: &Vec<u8>) -> Result<(), StdError> {
-        match &self.api {
+    /// Reads the elements in `range` of a uniform or storage buffer, e.g. an array written by a compute pass.
+    /// Fails if the range reaches past the end of the buffer.
+    pub fn read_buffer_slice<T: Pod>(&mut self, key: K, range: Range<usize>) -> Result<Vec<T>, StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.read_buffer_slice(key, range),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    /// Replaces the buffer's contents and size.
+    pub fn set_uniform_buffer_data<T: Pod>(&mut self, key: K, data: &[T]) -> Result<(), StdError> {
+        match &mut self.api {
             RendererAPI::OPEN_GL(gl) => gl.set_buffer_data(key, data),
             RendererAPI::VULKAN(_) => todo!(),
         }
     }
+    /// Overwrites elements starting at element `offset`, fails if the data reaches past the end of the buffer.
+    pub fn write_buffer_slice<T: Pod>(&mut self, key: K, offset: usize, data: &[T]) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.write_buffer_slice(key, offset, data),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    /// Starts copying elements `range` on the GPU, the data is picked up later with `try_readback` or `wait_readback`.
+    pub fn read_buffer_async<T: Pod>(&mut self, key: K, range: Range<usize>) -> Result<ReadbackHandle, StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.read_buffer_async::<T>(key, range),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    /// Starts reading a whole mip level back, tightly packed in the texture's own format.
+    pub fn read_texture_async(&mut self, key: K, level: u32) -> Result<ReadbackHandle, StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.read_texture_async(key, level),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    /// Starts reading RGBA8 pixels of the window, `x` and `y` from the bottom left and rows bottom to top.
+    pub fn read_pixels_async(&mut self, x: i32, y: i32, width: u32, height: u32) -> Result<ReadbackHandle, StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.read_pixels_async(x, y, width, height),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    /// Whether the GPU is done with the readback.
+    pub fn poll_readback(&self, handle: ReadbackHandle) -> Result<bool, StdError> {
+        match &self.api {
+            RendererAPI::OPEN_GL(gl) => gl.poll_readback(handle),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    /// Never blocks, returns `None` while the readback is pending. The handle is used up once data is returned.
+    pub fn try_readback<T: Pod>(&mut self, handle: ReadbackHandle) -> Result<Option<Vec<T>>, StdError> {
+        let data = match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.take_readback(handle, false)?,
+            RendererAPI::VULKAN(_) => todo!(),
+        };
+
+        Ok(data.map(|data| bytemuck::pod_collect_to_vec(&data)))
+    }
+    /// Blocks until the readback is done and uses the handle up.
+    pub fn wait_readback<T: Pod>(&mut self, handle: ReadbackHandle) -> Result<Vec<T>, StdError> {
+        let data = match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.take_readback(handle, true)?,
+            RendererAPI::VULKAN(_) => todo!(),
+        };
+
+        Ok(bytemuck::pod_collect_to_vec(&data.unwrap_or_default()))
+    }
+    /// Drops a readback that isn't needed anymore.
+    pub fn discard_readback(&mut self, handle: ReadbackHandle) {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.discard_readback(handle),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
 }
 impl<K: Clone + Default + Eq + PartialEq + Hash> LgRenderer<K> {
+    /// Unsets the current pipeline, but its blend, depth and raster state stays applied until another pipeline is set.
     pub fn set_program<S: LgShader>(&mut self, shaders: (K, &[(K, &S)])) -> Result<(), StdError> {
         match &mut self.api {
             RendererAPI::OPEN_GL(gl) => gl.set_program(shaders)?,
@@ -136,6 +339,31 @@ impl<K: Clone + Default + Eq + PartialEq + Hash> LgRenderer<K> {
         Ok(())
     }
 
+    /// Creating a pipeline again with the same key replaces it.
+    pub fn create_pipeline<S: LgShader>(&mut self, key: K, descriptor: PipelineDescriptor<K, S>) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.create_pipeline(key, descriptor)?,
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+        
+        Ok(())
+    }
+
+    /// Compressed formats the context can upload and sample.
+    pub fn compressed_formats(&self) -> Vec<CompressedFormat> {
+        match &self.api {
+            RendererAPI::OPEN_GL(gl) => gl.capabilities().compressed_formats(),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+
+    pub fn set_pipeline(&mut self, key: K) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.set_pipeline(key),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+
     pub fn set_vao(&mut self, id: K) -> Result<(), StdError> {
         match &mut self.api {
             RendererAPI::OPEN_GL(gl) => gl.set_vao(id)?,
@@ -145,13 +373,61 @@ impl<K: Clone + Default + Eq + PartialEq + Hash> LgRenderer<K> {
         Ok(())
     }
 
+    /// Same as `set_vertex_stream(0, VertexStep::VERTEX, vertices)`.
     pub fn set_vertices<V: GlVertex>(&mut self, vertices: &[V]) -> Result<(), StdError> {
+        self.set_vertex_stream(0, VertexStep::VERTEX, vertices)
+    }
+
+    /// Every stream of a vao has its own buffer, layout and step rate, locations can't be shared between streams.
+    /// Like `set_vertices` the data is only uploaded when the stream is created.
+    pub fn set_vertex_stream<V: GlVertex>(&mut self, stream: u32, step: VertexStep, vertices: &[V]) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.set_vertex_stream(stream, step, vertices),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+
+    /// Replaces the stream's data, layout and step rate even if the vao was already set up.
+    pub fn replace_vertex_stream<V: GlVertex>(
+        &mut self,
+        stream: u32,
+        step: VertexStep,
+        vertices: &[V],
+        usage: BufferUsage
+    ) -> Result<(), StdError> 
+    {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.replace_vertex_stream(stream, step, vertices, usage),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+
+    /// Overwrites vertices starting at vertex `first`, writing past the end grows the stream.
+    pub fn update_vertex_stream<V: GlVertex>(&mut self, stream: u32, first: usize, vertices: &[V]) -> Result<(), StdError> {
         match &mut self.api {
-            RendererAPI::OPEN_GL(gl) => gl.set_vertices(vertices),
+            RendererAPI::OPEN_GL(gl) => gl.update_vertex_stream(stream, first, vertices),
             RendererAPI::VULKAN(_) => todo!(),
         }
     }
 
+    /// The vertices are written to the streaming buffer and only valid for the current frame,
+    /// meant for data that changes every frame. `update_vertex_stream` fails until the stream is replaced.
+    pub fn set_transient_vertices<V: GlVertex>(&mut self, stream: u32, step: VertexStep, vertices: &[V]) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.set_transient_vertices(stream, step, vertices),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+
+    /// Disabled streams aren't fetched, e.g. to only feed positions to a depth pass.
+    pub fn set_vertex_stream_enabled(&mut self, stream: u32, enabled: bool) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.set_vertex_stream_enabled(stream, enabled),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+
+    /// Only uploaded the first time a vao is set up, a vao that has indices can also be drawn without calling this.
     pub fn set_indices(&mut self, indices: &[u32]) -> Result<(), StdError> {
         match &mut self.api {
             RendererAPI::OPEN_GL(gl) => gl.set_indices(indices),
@@ -159,6 +435,21 @@ impl<K: Clone + Default + Eq + PartialEq + Hash> LgRenderer<K> {
         }
     }
 
+    pub fn replace_indices(&mut self, indices: &[u32], usage: BufferUsage) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.replace_indices(indices, usage),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+
+    /// Overwrites indices starting at index `first`, writing past the end grows the index buffer and the index count.
+    pub fn update_indices(&mut self, first: usize, indices: &[u32]) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.update_indices(first, indices),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+
     pub fn set_uniforms(&mut self, ubos: Vec<(K, &impl LgUniform)>) -> Result<(), StdError> {
         match &mut self.api {
             RendererAPI::OPEN_GL(gl) => gl.set_uniforms(ubos),
@@ -166,18 +457,129 @@ impl<K: Clone + Default + Eq + PartialEq + Hash> LgRenderer<K> {
         }
     }
 
-    pub fn set_textures<T: LgTexture>(&mut self, textures: &[(K, &T, u32)]) -> Result<(), StdError> {
+    /// Like `set_uniforms` but the keys share a few large buffers, each one bound as an aligned range,
+    /// so many small per-object uniforms don't need a buffer each. Only uniform and storage blocks are allowed.
+    pub fn set_arena_uniforms(&mut self, ubos: Vec<(K, &impl LgUniform)>) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.set_arena_uniforms(ubos),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+
+    /// Frees the key's range in the uniform arena for reuse.
+    pub fn release_arena_uniform(&mut self, key: K) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.release_arena_uniform(key),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+
+    /// Like `set_uniforms` but the data is written to the streaming buffer and only valid for the current frame,
+    /// no buffer is kept per uniform. Only uniform and storage blocks are allowed.
+    pub fn set_transient_uniforms(&mut self, uniforms: &[&impl LgUniform]) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.set_transient_uniforms(uniforms),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+
+    /// (key, texture, binding, sampler), `None` uses `SamplerDescriptor::default()`.
+    /// Bindings by name are resolved against the program set last.
+    pub fn set_textures<T: LgTexture>(
+        &mut self,
+        textures: &[(K, &T, UniformBinding, Option<&SamplerDescriptor>)]
+    ) -> Result<(), StdError>
+    {
         match &mut self.api {
             RendererAPI::OPEN_GL(gl) => gl.set_textures(textures),
             RendererAPI::VULKAN(_) => todo!(),
         }
     }
+    /// (key, texture, binding) for `image*` uniforms. Barriers are issued before a written image is
+    /// sampled, updated or bound again, writes read back within the same draw or dispatch aren't covered.
+    pub fn set_images<T: LgTexture>(&mut self, images: &[(K, &T, ImageBinding)]) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.set_images(images),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    /// Runs the compute program set with `set_program`.
+    pub fn dispatch_compute(&mut self, groups_x: u32, groups_y: u32, groups_z: u32) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.dispatch_compute(groups_x, groups_y, groups_z),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    /// Replaces the pixels in `region` of an already created texture.
+    pub fn update_texture(&mut self, key: K, region: &TextureRegion, bytes: &[u8]) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.update_texture(key, region, bytes),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    /// Like `update_texture`, but goes through a staging buffer so the upload doesn't stall on pending draws.
+    /// Meant for textures that change every frame, like video.
+    pub fn stream_texture(&mut self, key: K, region: &TextureRegion, bytes: &[u8]) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.stream_texture(key, region, bytes),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    /// Resident bindless handle for `texture` sampled with `sampler` (`None` is the default sampler),
+    /// meant to be written into a storage buffer and indexed in shaders. Residency lasts until
+    /// `release_texture_handles`. Once a handle exists the texture's parameters are frozen, its texels
+    /// can still be updated, and loading it with a different size, format or mip count is an error.
+    pub fn texture_handle<T: LgTexture>(
+        &mut self,
+        key: K,
+        texture: &T,
+        sampler: Option<&SamplerDescriptor>
+    ) -> Result<u64, StdError>
+    {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.texture_handle(key, texture, sampler),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    pub fn release_texture_handles(&mut self, key: K) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.release_texture_handles(key),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    pub fn supports_bindless(&self) -> bool {
+        match &self.api {
+            RendererAPI::OPEN_GL(gl) => gl.capabilities().bindless_texture,
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    /// Uploads new atlas pages and the parts of existing ones that changed since the last call.
+    pub fn upload_atlas(&mut self, atlas: &mut TextureAtlas<K>) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.upload_atlas(atlas),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
+    /// Rebuilds the mip chain from the base level, e.g. after rendering into the texture.
+    /// Does nothing for textures with a single level.
+    pub fn generate_mipmaps(&mut self, key: K) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.generate_mipmaps(key),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
     pub fn draw(&mut self) -> Result<(), StdError> {
         match &mut self.api {
             RendererAPI::OPEN_GL(gl) => gl.draw(),
             RendererAPI::VULKAN(_) => todo!(),
         }
     }
+    pub fn draw_instances(&mut self, instance_count: u32) -> Result<(), StdError> {
+        match &mut self.api {
+            RendererAPI::OPEN_GL(gl) => gl.draw_instances(instance_count),
+            RendererAPI::VULKAN(_) => todo!(),
+        }
+    }
     pub fn draw_instanced<V: GlVertex>(&mut self, instance_data: &[V]) -> Result<(), StdError> {
         match &mut self.api {
             RendererAPI::OPEN_GL(gl) => gl.draw_instanced(instance_data),